@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+/// Ошибки работы с последовательным портом
+#[derive(Debug, Error)]
+pub enum PortError {
+    #[error("Failed to open port {port}: {source}")]
+    Open {
+        port: String,
+        #[source]
+        source: serialport::Error,
+    },
+    #[error("Unable to get port names: {0}")]
+    Enumerate(#[source] serialport::Error),
+    #[error("Failed to write to port {port}: {source}")]
+    Write {
+        port: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to read from port {port}: {source}")]
+    Read {
+        port: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Timeout has been reached on port {port}")]
+    Timeout { port: String },
+    #[error("Failed to clear input buffer {port}: {source}")]
+    Clear {
+        port: String,
+        #[source]
+        source: serialport::Error,
+    },
+    #[error("Failed to get available bytes {port}: {source}")]
+    BytesToRead {
+        port: String,
+        #[source]
+        source: serialport::Error,
+    },
+}
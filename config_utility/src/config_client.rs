@@ -1,11 +1,27 @@
 //use communication::serial_config::PortConfig;
-use log::{info, warn};
+use log::{debug, error, info, warn};
 use misc::device_config::DeviceConfig;
+use misc::parameter::{PARAMETERS, ParameterSpec};
 use misc::serial_config::PortConfig;
 use protocol::client::HostClient;
+use protocol::connection::ConnectionState;
+use protocol::device_info::{COMMIT_CAPABILITY, DeviceInfo};
+use protocol::error::ClientError;
+use protocol::event::DeviceEvent;
+use protocol::opcode::Opcode;
+use protocol::options::{ClientOptions, HandshakePolicy, ReplyMatcher, ResponseTimeouts};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::error::UtilityError;
+use crate::report::{
+    ConfigDiff, DiffEntry, ParameterReport, ParameterStatus, PushReport, RollbackStatus,
+};
 
 pub struct MUClient {
     mu_client: HostClient,
+    /// Переподключение после потери связи включено
+    reconnect: bool,
 }
 
 /// Режимы стриминга данных от устройства
@@ -16,79 +32,484 @@ pub enum StreamingMode {
     OnChangeMode = 1,
     /// Отправка данных периодически, с заданным в настройках (periodicity) периодом
     PeriodicMode = 2,
-    /// Отправка данных по требованию
+    /// Отправка данных по требованию (`MUClient::poll_status`)
     OnDemandMode = 3,
 }
 
 impl MUClient {
-    pub fn new(serial_config: &PortConfig) -> Result<Self, String> {
+    pub fn new(serial_config: &PortConfig) -> Result<Self, UtilityError> {
+        let options = ClientOptions {
+            timeouts: ResponseTimeouts {
+                response: serial_config.get_response_timeout(),
+                inter_byte: serial_config.get_inter_byte_timeout(),
+            },
+            handshake: HandshakePolicy {
+                request: serial_config.get_handshake_request(),
+                reply: ReplyMatcher::from(serial_config.get_handshake_reply().as_str()),
+                retry: serial_config.get_handshake_retry(),
+                query_info: serial_config.get_query_device_info(),
+                sequence_ids: serial_config.get_sequence_ids(),
+                checksum: serial_config.get_handshake_checksum(),
+            },
+            retry: serial_config.get_request_retry(),
+            checksum: serial_config.get_checksum(),
+            heartbeat: serial_config.get_heartbeat(),
+        };
+        let reconnect = options
+            .heartbeat
+            .as_ref()
+            .is_some_and(|policy| policy.reconnect);
+
         let client = HostClient::connect(
             serial_config.get_port_name().as_str(),
             serial_config.get_baud_rate(),
-            std::time::Duration::from_millis(5000),
-        )
-        .map_err(|e| e.to_string())?;
+            options,
+        )?;
 
-        info!("Connection established!");
+        match client.get_device_info() {
+            Some(device_info) => info!("Connection established: {}", device_info),
+            None => info!("Connection established!"),
+        }
 
-        Ok(Self { mu_client: client })
+        Ok(Self {
+            mu_client: client,
+            reconnect,
+        })
     }
 
-    /// Запрос сохраненных в устройстве настроек
-    pub fn get_settings_from_device(&mut self, config: &mut DeviceConfig) -> Result<(), String> {
-        let group_number = self.mu_client.send_request("get groupnumber")?;
+    /// Текущее состояние соединения с устройством
+    pub fn get_connection_state(&self) -> ConnectionState {
+        self.mu_client.get_connection_state()
+    }
 
-        let music_volume_idx = self.mu_client.send_request("get musicvolume")?;
+    /// Соединение восстанавливается при следующем запросе после потери связи
+    pub fn can_reconnect(&self) -> bool {
+        self.reconnect
+    }
+
+    /// Сведения о подключенном устройстве
+    pub fn get_device_info(&self) -> Option<&DeviceInfo> {
+        self.mu_client.get_device_info()
+    }
 
-        let sound_volume_idx = self.mu_client.send_request("get soundvolume")?;
+    /// Проверка поддержки параметров утилиты прошивкой устройства
+    fn ensure_parameters_supported(&self) -> Result<(), UtilityError> {
+        let Some(device_info) = self.get_device_info() else {
+            return Ok(());
+        };
 
-        let load_capacity_idx = self.mu_client.send_request("get loadcapacity")?;
+        // Необязательные параметры без поддержки прошивкой пропускаются
+        match PARAMETERS
+            .iter()
+            .find(|spec| !spec.optional && !device_info.supports_parameter(spec.key))
+        {
+            Some(spec) => Err(UtilityError::Unsupported {
+                parameter: spec.key.to_string(),
+                model: device_info.model.clone(),
+                firmware: device_info.firmware_version.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
 
-        config.set_group_number(group_number.try_into()?)?;
-        config.set_music_volume_idx(music_volume_idx.try_into()?)?;
-        config.set_sound_volume_idx(sound_volume_idx.try_into()?)?;
-        config.set_load_capacity_idx(load_capacity_idx.try_into()?)?;
+    /// Запрос сохраненных в устройстве настроек
+    pub fn get_settings_from_device(
+        &mut self,
+        config: &mut DeviceConfig,
+    ) -> Result<(), UtilityError> {
+        self.ensure_parameters_supported()?;
+        self.read_settings(config)?;
 
         warn!("#Config from device: {}", config);
 
         Ok(())
     }
 
+    /// Сравнение локального конфига с настройками устройства
+    pub fn diff_settings(&mut self, config: &DeviceConfig) -> Result<ConfigDiff, UtilityError> {
+        self.ensure_parameters_supported()?;
+
+        let mut device = DeviceConfig::new("device");
+        self.read_settings(&mut device)?;
+
+        Ok(self.compare(config, &device))
+    }
+
+    /// Сравнение значений параметров, которыми утилита обменивается с прошивкой
+    fn compare(&self, local: &DeviceConfig, device: &DeviceConfig) -> ConfigDiff {
+        let entries = self
+            .parameter_values(local)
+            .into_iter()
+            .map(|(parameter, value)| DiffEntry {
+                parameter,
+                local: value,
+                device: device.get_value(parameter).unwrap_or(value),
+            })
+            .collect();
+        ConfigDiff { entries }
+    }
+
+    /// Параметр участвует в обмене с прошивкой
+    ///
+    /// Необязательные параметры появились в новых прошивках, поэтому без сведений
+    /// об устройстве они не читаются и не записываются
+    fn is_exchanged(&self, spec: &ParameterSpec) -> bool {
+        !spec.optional
+            || self
+                .get_device_info()
+                .is_some_and(|info| info.supports_parameter(spec.key))
+    }
+
+    /// Значения параметров конфига, которыми утилита обменивается с прошивкой
+    fn parameter_values(&self, config: &DeviceConfig) -> Vec<(&'static str, u8)> {
+        config
+            .parameters()
+            .filter(|(spec, _)| self.is_exchanged(spec))
+            .map(|(spec, value)| (spec.key, value))
+            .collect()
+    }
+
+    /// Значения параметров конфига из списка `keys`
+    fn selected_values(&self, config: &DeviceConfig, keys: &[&str]) -> Vec<(&'static str, u8)> {
+        self.parameter_values(config)
+            .into_iter()
+            .filter(|(key, _)| keys.contains(key))
+            .collect()
+    }
+
+    /// Чтение всех параметров устройства в конфиг (одним конвейером запросов)
+    fn read_settings(&mut self, config: &mut DeviceConfig) -> Result<(), UtilityError> {
+        let keys = self
+            .parameter_values(config)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect::<Vec<&'static str>>();
+
+        // Значение вне диапазона параметра - ошибка ответа устройства, а не конфига
+        for (key, value) in self.read_parameters(&keys)? {
+            config
+                .set_value(key, value)
+                .map_err(|_| UtilityError::InvalidReply {
+                    parameter: key,
+                    value: value.to_string(),
+                })?;
+        }
+
+        Ok(())
+    }
+
     /// Отправка новых настроек на устройство для последующего сохранения
-    pub fn push_settings_to_device(&mut self, config: &DeviceConfig) -> Result<(), String> {
-        let group_number: String = config.get_group_number().try_into()?;
+    ///
+    /// Отправляются только параметры, значения которых отличаются от прочитанных
+    /// с устройства (все параметры при `all`). Ответ на каждую команду `set`
+    /// проверяется. При `verify` отправленные параметры затем читаются обратно
+    /// и сравниваются с отправленными значениями.
+    ///
+    /// Перед записью текущие настройки устройства сохраняются и восстанавливаются,
+    /// если хотя бы один параметр не записан. Прошивки с поддержкой `commit`
+    /// фиксируют настройки только после успешной записи всех параметров
+    pub fn push_settings_to_device(
+        &mut self,
+        config: &DeviceConfig,
+        verify: bool,
+        all: bool,
+    ) -> Result<PushReport, UtilityError> {
+        self.ensure_parameters_supported()?;
+
+        let mut snapshot = DeviceConfig::new("snapshot");
+        self.read_settings(&mut snapshot)?;
+        debug!("#Config before push: {}", snapshot);
+
+        let (changed, unchanged): (Vec<DiffEntry>, Vec<DiffEntry>) = self
+            .compare(config, &snapshot)
+            .entries
+            .into_iter()
+            .partition(|entry| all || entry.is_changed());
+        let keys = changed
+            .iter()
+            .map(|entry| entry.parameter)
+            .collect::<Vec<&'static str>>();
 
-        // TODO: check response, паттерн "Декоратор"
+        let outcome = self
+            .apply_settings(config, &keys, verify)
+            .and_then(|mut report| {
+                report.unchanged = unchanged.iter().map(|entry| entry.parameter).collect();
+                if report.is_success() && !report.entries.is_empty() && self.supports_commit() {
+                    self.mu_client.commit()?;
+                    report.committed = true;
+                }
+                Ok(report)
+            });
 
-        self.mu_client
-            .send_request(format!("set {}", group_number).as_str())?;
+        match outcome {
+            Ok(report) if report.is_success() => {
+                warn!("#Config to device: {}", config);
+                Ok(report)
+            }
+            Ok(mut report) => {
+                report.rollback = Some(self.restore_settings(&snapshot, &keys));
+                Ok(report)
+            }
+            Err(e) => match self.restore_settings(&snapshot, &keys) {
+                RollbackStatus::Restored => Err(e),
+                RollbackStatus::Failed(reason) => Err(UtilityError::RollbackFailed {
+                    cause: e.to_string(),
+                    reason,
+                }),
+            },
+        }
+    }
+
+    /// Запись параметров `keys` с проверкой ответов
+    ///
+    /// Команды `set` (и чтение для проверки) отправляются конвейером
+    fn apply_settings(
+        &mut self,
+        config: &DeviceConfig,
+        keys: &[&str],
+        verify: bool,
+    ) -> Result<PushReport, UtilityError> {
+        let mut report = PushReport::default();
 
-        let music_volume_idx: String = config.get_music_volume_idx().try_into()?;
+        let values = self.selected_values(config, keys);
+        let requests = values
+            .iter()
+            .map(|(parameter, value)| (*parameter, u32::from(*value)))
+            .collect::<Vec<(&str, u32)>>();
 
-        self.mu_client
-            .send_request(format!("set {}", music_volume_idx).as_str())?;
+        let results = self.mu_client.set_parameters(&requests)?;
+        for ((parameter, sent), result) in values.into_iter().zip(results) {
+            let status = match result {
+                Ok(()) => ParameterStatus::Applied,
+                Err(ClientError::Rejected { code, .. }) => {
+                    warn!("Device rejected {} = {} (ERR {})", parameter, sent, code);
+                    ParameterStatus::Rejected(code)
+                }
+                Err(e) => return Err(e.into()),
+            };
 
-        let sound_volume_idx: String = config.get_sound_volume_idx().try_into()?;
+            report.entries.push(ParameterReport {
+                parameter,
+                sent,
+                status,
+            });
+        }
 
-        self.mu_client
-            .send_request(format!("set {}", sound_volume_idx).as_str())?;
+        if verify {
+            self.verify_settings(&mut report)?;
+        }
 
-        let load_capacity_idx: String = config.get_load_capacity_idx().try_into()?;
+        Ok(report)
+    }
 
-        self.mu_client
-            .send_request(format!("set {}", load_capacity_idx).as_str())?;
+    /// Чтение записанных параметров и сравнение с отправленными значениями
+    fn verify_settings(&mut self, report: &mut PushReport) -> Result<(), UtilityError> {
+        let keys = report
+            .entries
+            .iter()
+            .filter(|entry| entry.status == ParameterStatus::Applied)
+            .map(|entry| entry.parameter)
+            .collect::<Vec<&'static str>>();
 
-        warn!("#Config to device: {}", config);
+        for (key, read_back) in self.read_parameters(&keys)? {
+            if let Some(entry) = report.entry_mut(key) {
+                entry.status = if read_back == entry.sent {
+                    ParameterStatus::Verified
+                } else {
+                    ParameterStatus::Mismatch { read_back }
+                };
+            }
+        }
 
         Ok(())
     }
 
+    /// Восстановление параметров `keys`, сохраненных перед отправкой
+    fn restore_settings(&mut self, snapshot: &DeviceConfig, keys: &[&str]) -> RollbackStatus {
+        warn!("Push failed, restoring previous settings: {}", snapshot);
+
+        let values = self.selected_values(snapshot, keys);
+        let requests = values
+            .iter()
+            .map(|(parameter, value)| (*parameter, u32::from(*value)))
+            .collect::<Vec<(&str, u32)>>();
+
+        let results = match self.mu_client.set_parameters(&requests) {
+            Ok(results) => results,
+            Err(e) => {
+                error!("Unable to restore settings: {}", e);
+                return RollbackStatus::Failed(e.to_string());
+            }
+        };
+
+        for ((parameter, _), result) in values.iter().zip(results) {
+            if let Err(e) = result {
+                error!("Unable to restore {}: {}", parameter, e);
+                return RollbackStatus::Failed(format!("{}: {}", parameter, e));
+            }
+        }
+
+        RollbackStatus::Restored
+    }
+
+    /// Прошивка фиксирует настройки командой `commit`
+    fn supports_commit(&self) -> bool {
+        self.get_device_info()
+            .is_some_and(|info| info.supports_capability(COMMIT_CAPABILITY))
+    }
+
+    /// Подписка на состояние лифта из потоковых данных устройства
+    ///
+    /// Пакеты принимаются в фоне и не мешают обмену запросами,
+    /// пакеты с некорректными данными пропускаются
+    pub fn subscribe_stream(&mut self) -> Receiver<DeviceEvent> {
+        let frames = self.mu_client.subscribe();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for frame in frames {
+                if frame.get_opcode() != Opcode::Streaming {
+                    continue;
+                }
+
+                match DeviceEvent::from_payload(frame.get_data()) {
+                    Ok(event) => {
+                        if sender.send(event).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => warn!("Bad streaming data {:?}: {}", frame.get_data(), e),
+                }
+            }
+        });
+
+        receiver
+    }
+
+    /// Чтение журнала устройства
+    pub fn read_log(&mut self) -> Result<Vec<String>, UtilityError> {
+        Ok(self.mu_client.read_log()?)
+    }
+
+    /// Запрос текущего состояния лифта (режим OnDemandMode)
+    pub fn poll_status(&mut self) -> Result<DeviceEvent, UtilityError> {
+        Ok(self.mu_client.poll_status()?)
+    }
+
     /// Запрос начала стриминга данных со станции управления
-    pub fn start_data_streaming(&mut self, mode: StreamingMode) -> Result<String, String> {
-        let response = self
-            .mu_client
-            .send_request(format!("set mode {}", mode as u8).as_str())?;
+    pub fn start_data_streaming(&mut self, mode: StreamingMode) -> Result<(), UtilityError> {
+        self.mu_client.set_streaming_mode(mode as u8)?;
+
+        Ok(())
+    }
+
+    /// Чтение параметров с устройства конвейером запросов
+    fn read_parameters(
+        &mut self,
+        keys: &[&'static str],
+    ) -> Result<Vec<(&'static str, u8)>, UtilityError> {
+        let values = self.mu_client.get_parameters(keys)?;
+
+        keys.iter()
+            .zip(values)
+            .map(|(&key, value)| {
+                u8::try_from(value).map(|value| (key, value)).map_err(|_| {
+                    UtilityError::InvalidReply {
+                        parameter: key,
+                        value: value.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EXIT_PROTOCOL;
+    use protocol::checksum::Checksum;
+    use protocol::decoder::FrameDecoder;
+    use protocol::fragment::{Message, Reassembler};
+    use protocol::options::RetryPolicy;
+    use protocol::transport::MemoryPipe;
+    use std::io::{Read, Write};
+    use std::process::ExitCode;
+    use std::time::Duration;
+
+    /// Модель устройства: отвечает на приветствие и на `get` значениями из `values`
+    /// (остальные параметры - 0)
+    fn spawn_device(mut pipe: MemoryPipe, values: Vec<(&'static str, u32)>) {
+        thread::spawn(move || {
+            let mut decoder = FrameDecoder::new();
+            let mut reassembler = Reassembler::new();
+            let mut buf = [0; 64];
+            loop {
+                let size = match pipe.read(&mut buf) {
+                    Ok(0) => return,
+                    Ok(size) => size,
+                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                    Err(_) => return,
+                };
+                decoder.push(&buf[..size]);
+
+                while let Some(frame) = decoder.next_frame() {
+                    let Some(request) = reassembler.push(frame).unwrap() else {
+                        continue;
+                    };
+                    let request = String::from_utf8(request.get_data().to_vec()).unwrap();
+                    let reply = match request.trim_end().strip_prefix("get ") {
+                        Some(key) => {
+                            let value = values
+                                .iter()
+                                .find(|(name, _)| *name == key)
+                                .map_or(0, |(_, value)| *value);
+                            format!("{}:{}\r\n", key, value)
+                        }
+                        None => "Hi!\r\n".to_string(),
+                    };
+                    let reply = Message::new(Opcode::Console, reply.into_bytes()).unwrap();
+                    for frame in reply.to_frames(Checksum::Crc8).unwrap() {
+                        pipe.write_all(&frame.serialize()).unwrap();
+                    }
+                }
+            }
+        });
+    }
+
+    fn connect(values: Vec<(&'static str, u32)>) -> MUClient {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        spawn_device(device, values);
+
+        let options = ClientOptions {
+            handshake: HandshakePolicy {
+                retry: RetryPolicy::none(),
+                ..HandshakePolicy::default()
+            },
+            ..ClientOptions::default()
+        };
+        MUClient {
+            mu_client: HostClient::with_transport(host, options).unwrap(),
+            reconnect: false,
+        }
+    }
+
+    #[test]
+    fn test_invalid_device_value_exit_code() {
+        // Значение в пределах u8, но вне диапазона параметра, и значение больше u8
+        for value in [20, 300] {
+            let mut client = connect(vec![("groupnumber", value)]);
+            let mut config = DeviceConfig::new("device");
 
-        Ok(response)
+            let error = client.get_settings_from_device(&mut config).unwrap_err();
+            assert!(matches!(
+                error,
+                UtilityError::InvalidReply {
+                    parameter: "groupnumber",
+                    ..
+                }
+            ));
+            assert_eq!(error.exit_code(), ExitCode::from(EXIT_PROTOCOL));
+        }
     }
 }
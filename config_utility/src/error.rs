@@ -0,0 +1,89 @@
+use std::process::ExitCode;
+
+use misc::config::ConfigError;
+use protocol::error::ClientError;
+use thiserror::Error;
+
+/// Коды завершения процесса (по мотивам sysexits.h)
+const EXIT_DATA_ERROR: u8 = 65;
+const EXIT_UNAVAILABLE: u8 = 69;
+const EXIT_IO_ERROR: u8 = 74;
+const EXIT_TEMP_FAIL: u8 = 75;
+pub(crate) const EXIT_PROTOCOL: u8 = 76;
+const EXIT_CONFIG: u8 = 78;
+
+/// Ошибки утилиты конфигурирования
+#[derive(Debug, Error)]
+pub enum UtilityError {
+    #[error("Device communication error: {0}")]
+    Client(#[from] ClientError),
+    #[error("Config error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("Device did not accept {failed} of {total} parameters, previous settings restored")]
+    PushIncomplete { failed: usize, total: usize },
+    #[error("Push failed ({cause}) and previous settings could not be restored: {reason}")]
+    RollbackFailed { cause: String, reason: String },
+    #[error("Device replied with invalid {parameter} value: {value}")]
+    InvalidReply {
+        parameter: &'static str,
+        value: String,
+    },
+    #[error("Parameter {parameter} is not supported by {model} (firmware {firmware})")]
+    Unsupported {
+        parameter: String,
+        model: String,
+        firmware: String,
+    },
+}
+
+impl UtilityError {
+    /// Код завершения процесса, соответствующий ошибке
+    pub fn exit_code(&self) -> ExitCode {
+        let code = match self {
+            UtilityError::Client(client_error) => match client_error {
+                ClientError::PortOpen { .. }
+                | ClientError::ConnectionClosed
+                | ClientError::ReconnectUnavailable => EXIT_UNAVAILABLE,
+                ClientError::Timeout => EXIT_TEMP_FAIL,
+                ClientError::HandshakeFailed { .. }
+                | ClientError::UnexpectedReply { .. }
+                | ClientError::Rejected { .. }
+                | ClientError::IncompatibleProtocol { .. }
+                | ClientError::Frame(_)
+                | ClientError::Encoding(_) => EXIT_PROTOCOL,
+                ClientError::Io(_) => EXIT_IO_ERROR,
+            },
+            UtilityError::Config(ConfigError::OutOfRange { .. }) => EXIT_DATA_ERROR,
+            UtilityError::Config(_) => EXIT_CONFIG,
+            UtilityError::PushIncomplete { .. } => EXIT_DATA_ERROR,
+            UtilityError::RollbackFailed { .. } | UtilityError::InvalidReply { .. } => {
+                EXIT_PROTOCOL
+            }
+            UtilityError::Unsupported { .. } => EXIT_UNAVAILABLE,
+        };
+        ExitCode::from(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_exit_codes() {
+        // Некорректное значение в локальном файле конфига
+        let local = UtilityError::from(ConfigError::InvalidValue {
+            section: "device_settings".to_string(),
+            key: "group_number".to_string(),
+            value: "abc".to_string(),
+        });
+        assert_eq!(local.exit_code(), ExitCode::from(EXIT_CONFIG));
+
+        // Некорректное значение в ответе устройства
+        let reply = UtilityError::InvalidReply {
+            parameter: "groupnumber",
+            value: "300".to_string(),
+        };
+        assert_eq!(reply.exit_code(), ExitCode::from(EXIT_PROTOCOL));
+    }
+}
@@ -5,35 +5,84 @@
 // $env:RUST_LOG="trace"
 // ./executable
 pub mod config_client;
+pub mod error;
+pub mod report;
 
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::str::FromStr;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use config_client::{MUClient, StreamingMode};
-use log::{debug, warn};
-use misc::config::ConfigIO;
+use error::UtilityError;
+use log::{debug, error, warn};
+use misc::config::{ConfigIO, config_root, set_config_root};
 use misc::serial_config::PortConfig;
+use report::RollbackStatus;
 
 use misc::device_config::DeviceConfig;
+use protocol::connection::ConnectionState;
+use protocol::error::ClientError;
 
 use clap::Parser;
 
+/// Период проверки состояния соединения в режиме monitor
+const STATE_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+/// Пауза перед повторным переподключением в режиме monitor
+const RECONNECT_PAUSE: Duration = Duration::from_secs(1);
+
 #[derive(Parser)]
 #[command(author = "Akimov Dmitry", name = "config_utility", version = "0.1.0", about, long_about = None)]
 struct Args {
     /// Имя конфиг файла
     #[arg(short = 'c', long = "config")]
     config_name: String,
-    /// Тип команды: pull - запрос сохраненных в устройстве настроек, push - отправка новых настроек
+    /// Тип команды: pull - запрос сохраненных в устройстве настроек, push - отправка новых настроек,
+    /// diff - сравнение локального конфига с настройками устройства,
+    /// monitor - вывод потоковых данных, log - вывод журнала устройства
     #[arg(short = 'm', long = "mode")]
     mode: CommandMode,
+    /// Проверка отправленных настроек повторным чтением с устройства
+    #[arg(long = "verify")]
+    verify: bool,
+    /// Отправка всех параметров, а не только отличающихся от настроек устройства
+    #[arg(long = "all")]
+    all: bool,
+    /// Длительность наблюдения в режиме monitor, с
+    #[arg(long = "duration", default_value_t = 10)]
+    duration_secs: u64,
+    /// Опрос состояния с заданным периодом в режиме monitor (OnDemandMode), мс
+    #[arg(long = "poll-ms")]
+    poll_ms: Option<u64>,
+    /// Корневой каталог конфигов (подкаталоги device/ и serial/)
+    #[arg(long = "config-root")]
+    config_root: Option<PathBuf>,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
     let args = Args::parse();
 
+    env_logger::init();
+
     warn!("Command mode: {:?}", args.mode);
 
-    env_logger::init();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            eprintln!("Error: {}", e);
+            e.exit_code()
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), UtilityError> {
+    if let Some(root) = &args.config_root {
+        set_config_root(root);
+    }
+    debug!("Config root: {}", config_root().display());
 
     let port_config = PortConfig::create_from_existing("pizero")?;
 
@@ -46,7 +95,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match args.mode {
         CommandMode::Pull => pull_command_handler(&mut device_config, &mut client)?,
-        CommandMode::Push => push_command_handler(&device_config, &mut client)?,
+        CommandMode::Push => {
+            push_command_handler(&device_config, &mut client, args.verify, args.all)?
+        }
+        CommandMode::Diff => diff_command_handler(&device_config, &mut client)?,
+        CommandMode::Log => {
+            for line in client.read_log()? {
+                println!("{}", line);
+            }
+        }
+        CommandMode::Monitor => {
+            let duration = Duration::from_secs(args.duration_secs);
+            match args.poll_ms {
+                Some(period) => {
+                    poll_command_handler(&mut client, duration, Duration::from_millis(period))?
+                }
+                None => monitor_command_handler(&mut client, duration)?,
+            }
+        }
     }
 
     Ok(())
@@ -56,18 +122,119 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn pull_command_handler(
     user_config: &mut DeviceConfig,
     client: &mut MUClient,
-) -> Result<(), String> {
+) -> Result<(), UtilityError> {
     client.get_settings_from_device(user_config)?;
     user_config.save_parameters()?;
     Ok(())
 }
 
+/// Сравнение локального конфига с настройками устройства
+fn diff_command_handler(
+    user_config: &DeviceConfig,
+    client: &mut MUClient,
+) -> Result<(), UtilityError> {
+    let diff = client.diff_settings(user_config)?;
+    println!("Local config vs device:\n{}", diff);
+    Ok(())
+}
+
 /// Отправка настроек на устройство
-fn push_command_handler(user_config: &DeviceConfig, client: &mut MUClient) -> Result<(), String> {
-    client.push_settings_to_device(user_config)?;
+fn push_command_handler(
+    user_config: &DeviceConfig,
+    client: &mut MUClient,
+    verify: bool,
+    all: bool,
+) -> Result<(), UtilityError> {
+    let report = client.push_settings_to_device(user_config, verify, all)?;
+    println!("Push report:\n{}", report);
+
+    if !report.is_success() {
+        let failed = report.failures().count();
+        let total = report.entries.len();
+
+        return Err(match report.rollback {
+            Some(RollbackStatus::Failed(reason)) => UtilityError::RollbackFailed {
+                cause: format!("{} of {} parameters not accepted", failed, total),
+                reason,
+            },
+            _ => UtilityError::PushIncomplete { failed, total },
+        });
+    }
+
+    client.start_data_streaming(StreamingMode::OnChangeMode)?;
+    warn!("Data streaming started");
+    Ok(())
+}
+
+/// Наблюдение за потоковыми данными устройства
+///
+/// После потери связи (если переподключение включено) наблюдение
+/// возобновляется на новом соединении. Если связь не восстановлена,
+/// возвращается ошибка потери связи, иначе по завершении стриминг отключается
+fn monitor_command_handler(client: &mut MUClient, duration: Duration) -> Result<(), UtilityError> {
+    let mut events = client.subscribe_stream();
+    client.start_data_streaming(StreamingMode::OnChangeMode)?;
+
+    // Ошибка последней попытки восстановить связь
+    let mut link_error = None;
+    let deadline = Instant::now() + duration;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let lost = match events.recv_timeout(remaining.min(STATE_CHECK_INTERVAL)) {
+            Ok(event) => {
+                println!("{}", event);
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                client.get_connection_state() == ConnectionState::Lost
+            }
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+        if !lost {
+            continue;
+        }
+
+        warn!("Device disconnected");
+        if !client.can_reconnect() {
+            return Err(ClientError::ConnectionClosed.into());
+        }
+
+        // Запрос выполняется после переподключения
+        match client.start_data_streaming(StreamingMode::OnChangeMode) {
+            Ok(()) => {
+                events = client.subscribe_stream();
+                link_error = None;
+                warn!("Monitoring resumed");
+            }
+            Err(e) => {
+                warn!("Reconnect failed: {}", e);
+                link_error = Some(e);
+                thread::sleep(RECONNECT_PAUSE.min(remaining));
+            }
+        }
+    }
+
+    if let Some(e) = link_error {
+        return Err(e);
+    }
+    client.start_data_streaming(StreamingMode::SilentMode)?;
+    Ok(())
+}
+
+/// Опрос состояния устройства по требованию
+fn poll_command_handler(
+    client: &mut MUClient,
+    duration: Duration,
+    period: Duration,
+) -> Result<(), UtilityError> {
+    client.start_data_streaming(StreamingMode::OnDemandMode)?;
+
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        println!("{}", client.poll_status()?);
+        thread::sleep(period.min(deadline.saturating_duration_since(Instant::now())));
+    }
 
-    let response = client.start_data_streaming(StreamingMode::OnChangeMode)?;
-    warn!("Start data streaming: {}", response);
+    client.start_data_streaming(StreamingMode::SilentMode)?;
     Ok(())
 }
 
@@ -75,6 +242,9 @@ fn push_command_handler(user_config: &DeviceConfig, client: &mut MUClient) -> Re
 enum CommandMode {
     Pull,
     Push,
+    Diff,
+    Monitor,
+    Log,
 }
 
 impl FromStr for CommandMode {
@@ -83,6 +253,9 @@ impl FromStr for CommandMode {
         match s {
             "pull" => Ok(CommandMode::Pull),
             "push" => Ok(CommandMode::Push),
+            "diff" => Ok(CommandMode::Diff),
+            "monitor" => Ok(CommandMode::Monitor),
+            "log" => Ok(CommandMode::Log),
             _ => Err(format!("Unknown command mode: {}", s)),
         }
     }
@@ -0,0 +1,245 @@
+use misc::parameter;
+use std::fmt::Display;
+
+/// Результат записи одного параметра
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParameterStatus {
+    /// Устройство подтвердило запись (`OK`)
+    Applied,
+    /// Запись подтверждена и значение прочитано обратно
+    Verified,
+    /// Устройство отклонило значение (`ERR <code>`)
+    Rejected(u8),
+    /// Запись подтверждена, но прочитано другое значение
+    Mismatch { read_back: u8 },
+}
+
+impl ParameterStatus {
+    pub fn is_success(&self) -> bool {
+        matches!(self, ParameterStatus::Applied | ParameterStatus::Verified)
+    }
+}
+
+impl Display for ParameterStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterStatus::Applied => write!(f, "applied"),
+            ParameterStatus::Verified => write!(f, "verified"),
+            ParameterStatus::Rejected(code) => write!(f, "rejected (ERR {})", code),
+            ParameterStatus::Mismatch { read_back } => {
+                write!(f, "mismatch (device reports {})", read_back)
+            }
+        }
+    }
+}
+
+/// Запись отчета об отправке параметра
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterReport {
+    pub parameter: &'static str,
+    pub sent: u8,
+    pub status: ParameterStatus,
+}
+
+/// Результат восстановления настроек после неудачной отправки
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RollbackStatus {
+    /// Предыдущие настройки восстановлены
+    Restored,
+    /// Восстановление не удалось (причина)
+    Failed(String),
+}
+
+/// Отчет об отправке настроек на устройство
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PushReport {
+    pub entries: Vec<ParameterReport>,
+    /// Настройки зафиксированы командой `commit`
+    pub committed: bool,
+    /// Результат отката, если отправка не удалась
+    pub rollback: Option<RollbackStatus>,
+    /// Параметры, не отправленные из-за совпадения со значением в устройстве
+    pub unchanged: Vec<&'static str>,
+}
+
+impl PushReport {
+    /// Все параметры записаны (и, если запрошено, проверены)
+    pub fn is_success(&self) -> bool {
+        self.entries.iter().all(|entry| entry.status.is_success())
+    }
+
+    /// Параметры, запись которых не удалась
+    pub fn failures(&self) -> impl Iterator<Item = &ParameterReport> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.status.is_success())
+    }
+
+    pub(crate) fn entry_mut(&mut self, parameter: &str) -> Option<&mut ParameterReport> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.parameter == parameter)
+    }
+}
+
+impl Display for PushReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.entries.is_empty() {
+            writeln!(f, " Device already matches local config")?;
+        }
+        for entry in &self.entries {
+            writeln!(
+                f,
+                " {:<14} {:>3}  {}",
+                entry.parameter, entry.sent, entry.status
+            )?;
+        }
+
+        if !self.unchanged.is_empty() {
+            writeln!(f, " Unchanged: {}", self.unchanged.join(", "))?;
+        }
+        if self.committed {
+            writeln!(f, " Settings committed")?;
+        }
+        match &self.rollback {
+            Some(RollbackStatus::Restored) => writeln!(f, " Previous settings restored"),
+            Some(RollbackStatus::Failed(reason)) => writeln!(f, " Rollback failed: {}", reason),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Значение параметра в локальном конфиге и в устройстве
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    pub parameter: &'static str,
+    pub local: u8,
+    pub device: u8,
+}
+
+impl DiffEntry {
+    pub fn is_changed(&self) -> bool {
+        self.local != self.device
+    }
+}
+
+/// Сравнение локального конфига с настройками устройства
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    pub entries: Vec<DiffEntry>,
+}
+
+impl ConfigDiff {
+    /// Параметры, значения которых отличаются
+    pub fn changes(&self) -> impl Iterator<Item = &DiffEntry> {
+        self.entries.iter().filter(|entry| entry.is_changed())
+    }
+
+    /// Локальный конфиг совпадает с настройками устройства
+    pub fn is_empty(&self) -> bool {
+        self.changes().next().is_none()
+    }
+}
+
+/// Значение параметра в виде для отображения
+fn display_value(parameter: &str, value: u8) -> String {
+    let raw = value.to_string();
+    match parameter::find(parameter).map(|spec| spec.display(value)) {
+        Some(shown) if shown != raw => format!("{} ({})", raw, shown),
+        _ => raw,
+    }
+}
+
+impl Display for ConfigDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "   {:<14} {:<24} device", "parameter", "local")?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                " {} {:<14} {:<24} {}",
+                if entry.is_changed() { '*' } else { ' ' },
+                entry.parameter,
+                display_value(entry.parameter, entry.local),
+                display_value(entry.parameter, entry.device)
+            )?;
+        }
+
+        match self.changes().count() {
+            0 => writeln!(f, " Device matches local config"),
+            changed => writeln!(
+                f,
+                " {} of {} parameters differ",
+                changed,
+                self.entries.len()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_report() {
+        let mut report = PushReport {
+            entries: vec![
+                ParameterReport {
+                    parameter: "groupnumber",
+                    sent: 3,
+                    status: ParameterStatus::Applied,
+                },
+                ParameterReport {
+                    parameter: "musicvolume",
+                    sent: 9,
+                    status: ParameterStatus::Rejected(3),
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert!(!report.is_success());
+        assert_eq!(
+            report.failures().map(|e| e.parameter).collect::<Vec<_>>(),
+            vec!["musicvolume"]
+        );
+        assert!(report.to_string().contains("rejected (ERR 3)"));
+
+        report.rollback = Some(RollbackStatus::Restored);
+        assert!(report.to_string().contains("Previous settings restored"));
+
+        report.entry_mut("musicvolume").unwrap().status = ParameterStatus::Verified;
+        assert!(report.is_success());
+    }
+
+    #[test]
+    fn test_config_diff() {
+        let mut diff = ConfigDiff {
+            entries: vec![
+                DiffEntry {
+                    parameter: "groupnumber",
+                    local: 3,
+                    device: 3,
+                },
+                DiffEntry {
+                    parameter: "soundvolume",
+                    local: 4,
+                    device: 2,
+                },
+            ],
+        };
+
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.changes().map(|e| e.parameter).collect::<Vec<_>>(),
+            vec!["soundvolume"]
+        );
+        let table = diff.to_string();
+        assert!(table.contains("4 (100 %)"));
+        assert!(table.contains("1 of 2 parameters differ"));
+
+        diff.entries[1].device = 4;
+        assert!(diff.is_empty());
+        assert!(diff.to_string().contains("Device matches local config"));
+    }
+}
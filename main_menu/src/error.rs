@@ -0,0 +1,38 @@
+use std::process::ExitCode;
+
+use communication::error::PortError;
+use inquire::InquireError;
+use misc::config::ConfigError;
+use thiserror::Error;
+
+/// Коды завершения процесса (по мотивам sysexits.h)
+const EXIT_DATA_ERROR: u8 = 65;
+const EXIT_UNAVAILABLE: u8 = 69;
+const EXIT_IO_ERROR: u8 = 74;
+const EXIT_CONFIG: u8 = 78;
+
+/// Ошибки главного меню
+#[derive(Debug, Error)]
+pub enum MenuError {
+    #[error("Config error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("Serial port error: {0}")]
+    Port(#[from] PortError),
+    #[error("Prompt error: {0}")]
+    Prompt(#[from] InquireError),
+    #[error("{0}")]
+    InvalidSelection(String),
+}
+
+impl MenuError {
+    /// Код завершения процесса, соответствующий ошибке
+    pub fn exit_code(&self) -> ExitCode {
+        let code = match self {
+            MenuError::Config(_) => EXIT_CONFIG,
+            MenuError::Port(_) => EXIT_UNAVAILABLE,
+            MenuError::Prompt(_) => EXIT_IO_ERROR,
+            MenuError::InvalidSelection(_) => EXIT_DATA_ERROR,
+        };
+        ExitCode::from(code)
+    }
+}
@@ -1,11 +1,27 @@
+mod error;
 mod main_menu;
 
-use log::{debug, warn};
+use std::process::ExitCode;
+
+use error::MenuError;
+use log::{debug, error, warn};
 use main_menu::{MainMenuStates, show_main_dialog};
 use misc::config::ConfigIO;
 use misc::serial_config::PortConfig;
-fn main() -> Result<(), String> {
+fn main() -> ExitCode {
     env_logger::init();
+
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            eprintln!("Error: {}", e);
+            e.exit_code()
+        }
+    }
+}
+
+fn run() -> Result<(), MenuError> {
     let mut config = PortConfig::create_new("default")?;
     loop {
         match show_main_dialog(&mut config) {
@@ -25,5 +41,5 @@ fn main() -> Result<(), String> {
         }
     }
 
-    return Ok(());
+    Ok(())
 }
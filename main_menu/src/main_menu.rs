@@ -1,3 +1,4 @@
+use crate::error::MenuError;
 use communication::serial_port::SerialInterface;
 use crossterm::style::Color;
 use inquire::validator::Validation;
@@ -14,13 +15,14 @@ const MAIN_MENU_MEMBERS: [&str; MAIN_MENU_ITEMS] = [
     "Выход",
 ];
 
+#[allow(clippy::enum_variant_names)]
 pub enum MainMenuStates {
     ConnectionRequestState,
     ConfigurationState,
     ExitState,
 }
 
-pub fn show_main_dialog(config: &mut PortConfig) -> Result<MainMenuStates, String> {
+pub fn show_main_dialog(config: &mut PortConfig) -> Result<MainMenuStates, MenuError> {
     // Создание структуры главного меню
     let main_menu = menu(vec![
         label("----------------------").colorize(Color::DarkGreen),
@@ -30,7 +32,7 @@ pub fn show_main_dialog(config: &mut PortConfig) -> Result<MainMenuStates, Strin
             env!("CARGO_PKG_VERSION")
         ))
         .colorize(Color::Green),
-        label(format!("{}", env!("CARGO_PKG_AUTHORS"))).colorize(Color::Green),
+        label(env!("CARGO_PKG_AUTHORS").to_string()).colorize(Color::Green),
         label("MU LLC, 2025").colorize(Color::DarkGreen),
         label("-----------------------").colorize(Color::Green),
         label("Текущая конфигурация порта").colorize(Color::DarkGreen),
@@ -49,23 +51,21 @@ pub fn show_main_dialog(config: &mut PortConfig) -> Result<MainMenuStates, Strin
 
     // Обработка пользовательского выбора
     match mut_menu(&main_menu).selected_item_name() {
-        val if val == MAIN_MENU_MEMBERS[0] => {
-            return Ok(MainMenuStates::ConnectionRequestState);
-        }
+        val if val == MAIN_MENU_MEMBERS[0] => Ok(MainMenuStates::ConnectionRequestState),
         val if val == MAIN_MENU_MEMBERS[1] => {
             *config = show_port_config_dialog()?;
-            return Ok(MainMenuStates::ConfigurationState);
+            Ok(MainMenuStates::ConfigurationState)
         }
         val if val == MAIN_MENU_MEMBERS[2] => {
             *config = show_load_config_dialog()?;
-            return Ok(MainMenuStates::ConfigurationState);
+            Ok(MainMenuStates::ConfigurationState)
         }
-        _ => return Ok(MainMenuStates::ExitState),
+        _ => Ok(MainMenuStates::ExitState),
     }
 }
 
 /// Отображение диалога создания конфигурации порта
-fn show_port_config_dialog() -> Result<PortConfig, String> {
+fn show_port_config_dialog() -> Result<PortConfig, MenuError> {
     let port_selection = show_port_names_dialog()?;
     let baud_selection = show_baudrate_dialog()?;
 
@@ -82,47 +82,47 @@ fn show_port_config_dialog() -> Result<PortConfig, String> {
     config.set_baud_rate(baud_selection);
     config.set_port_name(port_selection);
 
-    return Ok(config);
+    Ok(config)
 }
 
-fn show_load_config_dialog() -> Result<PortConfig, String> {
+fn show_load_config_dialog() -> Result<PortConfig, MenuError> {
     let config_files = PortConfig::list_existing_configs()?;
 
-    if config_files.len() > 0 {
+    if !config_files.is_empty() {
         let answer = Select::new("Выбор конфигурации", config_files).prompt();
         match answer {
             Ok(selection) => return Ok(PortConfig::create_from_existing(&selection)?),
-            Err(e) => return Err(e.to_string()),
+            Err(e) => return Err(e.into()),
         }
     }
 
-    return Ok(PortConfig::create_new("default")?);
+    Ok(PortConfig::create_new("default")?)
 }
 
 /// Отображение промпта "Выбор имени последовательного порта"
-fn show_port_names_dialog() -> Result<String, String> {
+fn show_port_names_dialog() -> Result<String, MenuError> {
     let port_names = SerialInterface::get_available_port_names()?;
     let answer = Select::new("Выбор последовательного порта", port_names).prompt();
     match answer {
         Ok(selection) => Ok(selection),
-        Err(e) => return Err(e.to_string()),
+        Err(e) => Err(e.into()),
     }
 }
 
 /// Отображение промпта "Выбор скорости порта"
-fn show_baudrate_dialog() -> Result<u32, String> {
+fn show_baudrate_dialog() -> Result<u32, MenuError> {
     let baud_rates = SerialInterface::get_supported_port_speed()?;
     let answer = Select::new("Выбор скорости порта", baud_rates).prompt();
     match answer {
         Ok(selection) => selection
             .parse::<u32>()
-            .map_err(|_| "Invalid baud rate!".to_string()),
-        Err(e) => return Err(e.to_string()),
+            .map_err(|_| MenuError::InvalidSelection("Invalid baud rate!".to_string())),
+        Err(e) => Err(e.into()),
     }
 }
 
 /// Отображение промпта "Выбор имени конфигурационного файла"
-fn show_get_filename_dialog() -> Result<String, String> {
+fn show_get_filename_dialog() -> Result<String, MenuError> {
     // Валидатор пользовательского ввода
     let config_name_validator = |s: &str| {
         if s.is_empty() {
@@ -147,7 +147,7 @@ fn show_get_filename_dialog() -> Result<String, String> {
 
     match name {
         Ok(name) => Ok(name),
-        Err(e) => return Err(e.to_string()),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -157,8 +157,5 @@ fn show_save_config_dialog() -> bool {
         .with_default(false)
         .prompt();
 
-    match decision {
-        Ok(true) => true,
-        _ => return false,
-    }
+    matches!(decision, Ok(true))
 }
@@ -1,23 +1,789 @@
+use crate::migration::Schema;
+use configparser::ini::Ini;
+use log::info;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+
 pub trait ConfigIO {
     /// Создание конфига с параметрами по умолчанию
     /// Сохранение конфига в файл ini
-    fn create_new(name: &str) -> Result<Self, String>
+    fn create_new(name: &str) -> Result<Self, ConfigError>
     where
         Self: Sized;
 
     /// Загрузка параметров существующего конфига с именем name
-    fn create_from_existing(name: &str) -> Result<Self, String>
+    fn create_from_existing(name: &str) -> Result<Self, ConfigError>
     where
         Self: Sized;
 
     fn get_config_name(&self) -> String;
 
+    /// Формат хранения файла конфига
+    fn get_format(&self) -> ConfigFormat;
+    /// Смена формата: следующее сохранение запишет файл с расширением формата
+    fn set_format(&mut self, format: ConfigFormat);
+
     /// Сохранение параметров конфига в файл с именем self.name
-    fn save_parameters(&self) -> Result<(), String>;
+    fn save_parameters(&self) -> Result<(), ConfigError>;
     /// Загрузка параметров конфига из файл с именем self.name
-    fn load_parameters(&mut self) -> Result<(), String>;
+    fn load_parameters(&mut self) -> Result<(), ConfigError>;
     /// Список существующих конфигов
-    fn list_existing_configs() -> Result<Vec<String>, String>;
+    fn list_existing_configs() -> Result<Vec<String>, ConfigError>;
+}
+
+/// Переменная окружения с корневым каталогом конфигов
+pub const CONFIG_ROOT_ENV: &str = "ADAMULTITOOL_CONFIG_ROOT";
+/// Каталог конфигов в `$XDG_CONFIG_HOME`
+const CONFIG_ROOT_NAME: &str = "adamultitool";
+/// Системный каталог конфигов
+const SYSTEM_CONFIG_ROOT: &str = "/etc/adamultitool";
+/// Каталог конфигов относительно рабочего каталога (исходное расположение)
+const LOCAL_CONFIG_ROOT: &str = "configs";
+
+/// Корневой каталог, заданный аргументом командной строки
+static CONFIG_ROOT_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Задание корневого каталога конфигов аргументом командной строки
+///
+/// Имеет приоритет над переменной окружения и каталогами по умолчанию
+pub fn set_config_root(root: impl Into<PathBuf>) {
+    *CONFIG_ROOT_OVERRIDE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some(root.into());
+}
+
+/// Корневой каталог конфигов (подкаталоги `device/` и `serial/`)
+///
+/// Порядок выбора: аргумент командной строки (`set_config_root`), переменная
+/// окружения `ADAMULTITOOL_CONFIG_ROOT`, затем первый существующий из
+/// `$XDG_CONFIG_HOME/adamultitool` (`~/.config/adamultitool`), `/etc/adamultitool`
+/// и `configs/` в рабочем каталоге. Если ни один из них не существует - каталог XDG
+pub fn config_root() -> PathBuf {
+    select_root(Path::new(SYSTEM_CONFIG_ROOT))
+}
+
+/// Выбор корневого каталога с заданным системным каталогом
+fn select_root(system_root: &Path) -> PathBuf {
+    let flag = CONFIG_ROOT_OVERRIDE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    if let Some(root) = flag {
+        return root;
+    }
+
+    if let Some(root) = std::env::var_os(CONFIG_ROOT_ENV).filter(|root| !root.is_empty()) {
+        return PathBuf::from(root);
+    }
+
+    let xdg_root = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|home| home.join(CONFIG_ROOT_NAME));
+
+    let candidates = [
+        xdg_root.clone(),
+        Some(system_root.to_path_buf()),
+        Some(PathBuf::from(LOCAL_CONFIG_ROOT)),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|root| root.is_dir())
+        .or(xdg_root)
+        .unwrap_or_else(|| PathBuf::from(LOCAL_CONFIG_ROOT))
+}
+
+/// Ошибки работы с конфигурацией
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Unable to load config {path}: {reason}")]
+    Load { path: String, reason: String },
+    #[error("Unable to save config {path}: {source}")]
+    Save {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Unable to get config file names from {path}: {source}")]
+    List {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Missing key {key} in section [{section}]")]
+    MissingKey { section: String, key: String },
+    #[error("{parameter} must be less than {}", max + 1)]
+    OutOfRange {
+        parameter: &'static str,
+        value: u32,
+        max: u32,
+    },
+    #[error("Unknown device parameter {0}")]
+    UnknownParameter(String),
+    #[error("Config schema version {found} is newer than supported version {supported}")]
+    SchemaVersion { found: u64, supported: u64 },
+    #[error("Invalid value {value:?} of {key} in section [{section}]")]
+    InvalidValue {
+        section: String,
+        key: String,
+        value: String,
+    },
+}
+
+/// Загрузка ini файла
+fn load_ini(path: &str) -> Result<Ini, ConfigError> {
+    let mut config_instance = Ini::new();
+    config_instance
+        .load(path)
+        .map_err(|reason| ConfigError::Load {
+            path: path.to_string(),
+            reason,
+        })?;
+    Ok(config_instance)
+}
+
+/// Сохранение ini файла
+fn write_ini(config_instance: &Ini, path: &str) -> Result<(), ConfigError> {
+    config_instance
+        .write(path)
+        .map_err(|source| ConfigError::Save {
+            path: path.to_string(),
+            source,
+        })
+}
+
+/// Формат хранения конфига, определяется по расширению файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Ini,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Все форматы в порядке поиска файла конфига
+    pub const ALL: [ConfigFormat; 3] = [ConfigFormat::Ini, ConfigFormat::Toml, ConfigFormat::Json];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Ini => "ini",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+        }
+    }
+
+    /// Формат по расширению файла
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// Имя конфига без расширения и формат, заданный расширением (если есть)
+    pub(crate) fn split_name(name: &str) -> (String, Option<Self>) {
+        match Self::from_path(Path::new(name)) {
+            Some(format) => {
+                let stem_len = name.len() - format.extension().len() - 1;
+                (name[..stem_len].to_string(), Some(format))
+            }
+            None => (name.to_string(), None),
+        }
+    }
+
+    /// Имя и формат существующего конфига в подкаталоге `dir` корневого каталога
+    ///
+    /// Без расширения в имени выбирается первый найденный файл
+    /// в порядке `ALL`, если файла нет - ini
+    pub(crate) fn locate(dir: &str, name: &str) -> (String, Self) {
+        match Self::split_name(name) {
+            (name, Some(format)) => (name, format),
+            (name, None) => {
+                let format = Self::ALL
+                    .into_iter()
+                    .find(|format| Path::new(&config_path(dir, &name, *format)).exists())
+                    .unwrap_or_default();
+                (name, format)
+            }
+        }
+    }
+}
+
+impl Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// Путь к файлу конфига в подкаталоге `dir` корневого каталога
+pub(crate) fn config_path(dir: &str, name: &str, format: ConfigFormat) -> String {
+    config_root()
+        .join(dir)
+        .join(format!("{}.{}", name, format.extension()))
+        .display()
+        .to_string()
+}
+
+/// Документ конфига: секции с типизированными значениями
+///
+/// Общее представление файлов ini, TOML и JSON, с которым работают миграции
+/// схемы. Ключи секций совпадают с именами полей serde структур конфига
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ConfigDocument(BTreeMap<String, BTreeMap<String, Value>>);
+
+impl ConfigDocument {
+    /// Документ конфига с текущей версией схемы
+    pub(crate) fn of<T: Serialize>(config: &T, schema: &Schema) -> serde_json::Result<Self> {
+        let mut document: Self = serde_json::from_value(serde_json::to_value(config)?)?;
+        schema.stamp(&mut document);
+        Ok(document)
+    }
+
+    /// Значение ключа секции
+    pub fn get(&self, section: &str, key: &str) -> Option<&Value> {
+        self.0.get(section)?.get(key)
+    }
+
+    /// Запись значения ключа секции
+    pub fn set(&mut self, section: &str, key: &str, value: impl Into<Value>) {
+        self.0
+            .entry(section.to_string())
+            .or_default()
+            .insert(key.to_string(), value.into());
+    }
+
+    /// Пары (секция, ключ, значение) всех ключей документа
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, &Value)> + '_ {
+        self.0.iter().flat_map(|(section, keys)| {
+            keys.iter()
+                .map(move |(key, value)| (section.as_str(), key.as_str(), value))
+        })
+    }
+
+    /// Конфиг из документа
+    pub(crate) fn parse<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_value(serde_json::to_value(self)?)
+    }
+
+    /// Документ из ini файла
+    ///
+    /// Значения ini хранятся текстом, тип значения берется из документа
+    /// `template` (конфига по умолчанию): числа и флаги (0/1) разбираются,
+    /// остальные значения остаются текстом
+    fn from_ini(config_instance: &Ini, template: &Self) -> Result<Self, ConfigError> {
+        let mut document = Self::default();
+        for (section, keys) in config_instance.get_map_ref() {
+            for (key, text) in keys {
+                let Some(text) = text else {
+                    continue;
+                };
+                let invalid = || ConfigError::InvalidValue {
+                    section: section.clone(),
+                    key: key.clone(),
+                    value: text.clone(),
+                };
+                let value = match template.get(section, key) {
+                    Some(Value::Number(_)) => {
+                        Value::from(text.trim().parse::<u64>().map_err(|_| invalid())?)
+                    }
+                    Some(Value::Bool(_)) => Value::Bool(match text.trim() {
+                        "1" | "true" => true,
+                        "0" | "false" => false,
+                        _ => return Err(invalid()),
+                    }),
+                    _ => Value::String(text.clone()),
+                };
+                document.set(section, key, value);
+            }
+        }
+        Ok(document)
+    }
+
+    /// Ini файл из документа (флаги записываются как 0/1)
+    fn to_ini(&self) -> Ini {
+        let mut config_instance = Ini::new();
+        for (section, key, value) in self.entries() {
+            let text = match value {
+                Value::Bool(flag) => u8::from(*flag).to_string(),
+                Value::String(text) => text.clone(),
+                value => value.to_string(),
+            };
+            config_instance.set(section, key, Some(text));
+        }
+        config_instance
+    }
+}
+
+/// Загрузка документа из файла в заданном формате
+fn load_document(
+    path: &str,
+    format: ConfigFormat,
+    template: &ConfigDocument,
+) -> Result<ConfigDocument, ConfigError> {
+    let load_error = |reason: String| ConfigError::Load {
+        path: path.to_string(),
+        reason,
+    };
+
+    match format {
+        ConfigFormat::Ini => ConfigDocument::from_ini(&load_ini(path)?, template),
+        ConfigFormat::Toml => {
+            let text = fs::read_to_string(path).map_err(|e| load_error(e.to_string()))?;
+            toml::from_str(&text).map_err(|e| load_error(e.to_string()))
+        }
+        ConfigFormat::Json => {
+            let text = fs::read_to_string(path).map_err(|e| load_error(e.to_string()))?;
+            serde_json::from_str(&text).map_err(|e| load_error(e.to_string()))
+        }
+    }
+}
+
+/// Сохранение документа в файл в заданном формате
+fn write_document(
+    document: &ConfigDocument,
+    path: &str,
+    format: ConfigFormat,
+) -> Result<(), ConfigError> {
+    let save_error = |source: std::io::Error| ConfigError::Save {
+        path: path.to_string(),
+        source,
+    };
+
+    // Каталог по умолчанию (XDG) при первом сохранении может отсутствовать
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir).map_err(save_error)?;
+    }
+
+    let text = match format {
+        ConfigFormat::Ini => return write_ini(&document.to_ini(), path),
+        ConfigFormat::Toml => toml::to_string(document).map_err(std::io::Error::other),
+        ConfigFormat::Json => serde_json::to_string_pretty(document).map_err(std::io::Error::other),
+    }
+    .map_err(save_error)?;
+    fs::write(path, text).map_err(save_error)
+}
+
+/// Загрузка файла конфига с приведением к текущей версии схемы
+///
+/// `template` - конфиг по умолчанию, задающий типы значений ini файла.
+/// Файл старой версии перезаписывается обновленным, исходный файл
+/// сохраняется рядом с суффиксом `.v<версия>.bak`
+pub(crate) fn load_migrated<T: Serialize + DeserializeOwned>(
+    path: &str,
+    format: ConfigFormat,
+    schema: &Schema,
+    template: &T,
+) -> Result<T, ConfigError> {
+    let load_error = |e: serde_json::Error| ConfigError::Load {
+        path: path.to_string(),
+        reason: e.to_string(),
+    };
+
+    let template = ConfigDocument::of(template, schema).map_err(load_error)?;
+    let mut document = load_document(path, format, &template)?;
+
+    if let Some(found) = schema.upgrade(&mut document)? {
+        let backup = format!("{}.v{}.bak", path, found);
+        fs::copy(path, &backup).map_err(|source| ConfigError::Save {
+            path: backup.clone(),
+            source,
+        })?;
+        write_document(&document, path, format)?;
+        info!(
+            "Config {} migrated from schema version {} to {}, backup saved to {}",
+            path,
+            found,
+            schema.version(),
+            backup
+        );
+    }
+
+    document.parse().map_err(load_error)
+}
+
+/// Сохранение файла конфига в заданном формате с текущей версией схемы
+pub(crate) fn write_config<T: Serialize>(
+    config: &T,
+    path: &str,
+    format: ConfigFormat,
+    schema: &Schema,
+) -> Result<(), ConfigError> {
+    let document = ConfigDocument::of(config, schema).map_err(|e| ConfigError::Save {
+        path: path.to_string(),
+        source: std::io::Error::other(e),
+    })?;
+    write_document(&document, path, format)
+}
+
+/// Длительность в миллисекундах (ключи `*_MS`)
+pub(crate) mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// Необязательная длительность в миллисекундах (0 - без ограничения)
+pub(crate) mod optional_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.map_or(0, |value| value.as_millis() as u64))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        let millis = u64::deserialize(deserializer)?;
+        Ok((millis > 0).then(|| Duration::from_millis(millis)))
+    }
+}
+
+/// Имена конфигов в подкаталоге `dir` корневого каталога
+/// (файлы поддерживаемых форматов, без расширения)
+pub(crate) fn list_configs(dir: &str) -> Result<Vec<String>, ConfigError> {
+    let dir = config_root().join(dir);
+    let entries = fs::read_dir(&dir).map_err(|source| ConfigError::List {
+        path: dir.display().to_string(),
+        source,
+    })?;
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|source| ConfigError::List {
+                path: dir.display().to_string(),
+                source,
+            })?
+            .path();
+        if ConfigFormat::from_path(&path).is_some()
+            && let Some(stem) = path.file_stem()
+        {
+            names.push(stem.to_string_lossy().to_string());
+        }
+    }
+    names.sort();
+    names.dedup();
+
+    Ok(names)
 }
 
-enum _ConfigIOError {}
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::device_config::DeviceConfig;
+    use crate::parameter::PERIODICITY;
+    use crate::serial_config::PortConfig;
+    use protocol::checksum::Checksum;
+    use protocol::options::{HandshakePolicy, HeartbeatMode, HeartbeatPolicy, ResponseTimeouts};
+    use std::ffi::OsString;
+    use std::sync::MutexGuard;
+
+    /// Пустой временной каталог теста
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("adamultitool-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn port_config() -> PortConfig {
+        let mut config = PortConfig::new("");
+        config.set_port_name("/dev/ttyUSB1".to_string());
+        config.set_baud_rate(115200);
+        // Текст из цифр остается текстом во всех форматах
+        config.set_handshake_request("0123".to_string());
+        config.set_handshake_reply("007".to_string());
+        config.set_sequence_ids(true);
+        config.set_handshake_checksum(Some(Checksum::Crc16));
+        config.set_heartbeat(Some(HeartbeatPolicy {
+            mode: HeartbeatMode::Traffic,
+            ..HeartbeatPolicy::default()
+        }));
+        config
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = temp_dir("round-trip");
+        let schema = Schema { migrations: &[] };
+
+        for format in ConfigFormat::ALL {
+            let path = dir.join(format!("port.{}", format)).display().to_string();
+            write_config(&port_config(), &path, format, &schema).unwrap();
+            let loaded: PortConfig =
+                load_migrated(&path, format, &schema, &PortConfig::new("")).unwrap();
+            assert_eq!(loaded, port_config(), "{}", format);
+            assert_eq!(loaded.get_handshake_request(), "0123");
+
+            let mut device = DeviceConfig::new("");
+            device.set_value("groupnumber", 7).unwrap();
+            let path = dir.join(format!("device.{}", format)).display().to_string();
+            write_config(&device, &path, format, &schema).unwrap();
+            let loaded: DeviceConfig =
+                load_migrated(&path, format, &schema, &DeviceConfig::new("")).unwrap();
+            assert_eq!(loaded.get_value("groupnumber"), Some(7), "{}", format);
+        }
+
+        // Поля документа - ключи ini файла, флаги записываются как 0/1
+        let text = fs::read_to_string(dir.join("port.ini")).unwrap();
+        assert!(text.contains("request=0123"));
+        assert!(text.contains("sequence_ids=1"));
+        let text = fs::read_to_string(dir.join("port.json")).unwrap();
+        assert!(text.contains("\"request\": \"0123\""));
+        assert!(text.contains("\"sequence_ids\": true"));
+        assert!(text.contains("\"backoff_ms\": 100"));
+        assert!(text.contains("\"mode\": \"traffic\""));
+        let text = fs::read_to_string(dir.join("device.toml")).unwrap();
+        assert!(text.contains("group_number = 7"));
+    }
+
+    #[test]
+    fn test_invalid_ini_value() {
+        let dir = temp_dir("invalid-value");
+        let path = dir.join("device.ini").display().to_string();
+        fs::write(&path, "[device_settings]\ngroup_number=three\n").unwrap();
+
+        let schema = Schema { migrations: &[] };
+        let result = load_migrated(&path, ConfigFormat::Ini, &schema, &DeviceConfig::new(""));
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidValue { ref key, .. }) if key == "group_number"
+        ));
+
+        // Неизвестная контрольная сумма отклоняется при загрузке
+        let path = dir.join("port.ini").display().to_string();
+        write_config(&PortConfig::new(""), &path, ConfigFormat::Ini, &schema).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::write(&path, text.replace("checksum=crc8", "checksum=md5")).unwrap();
+        let result = load_migrated(&path, ConfigFormat::Ini, &schema, &PortConfig::new(""));
+        assert!(matches!(
+            result,
+            Err(ConfigError::Load { ref reason, .. }) if reason.contains("md5")
+        ));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("configs/serial/pizero.ini")),
+            Some(ConfigFormat::Ini)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("pizero.TOML")),
+            Some(ConfigFormat::Toml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("pizero.json")),
+            Some(ConfigFormat::Json)
+        );
+        assert_eq!(ConfigFormat::from_path(Path::new("pizero")), None);
+        assert_eq!(ConfigFormat::from_path(Path::new("pizero.bak")), None);
+
+        assert_eq!(
+            ConfigFormat::split_name("pizero.json"),
+            ("pizero".to_string(), Some(ConfigFormat::Json))
+        );
+        assert_eq!(
+            ConfigFormat::split_name("pi.zero"),
+            ("pi.zero".to_string(), None)
+        );
+        assert_eq!(
+            ConfigFormat::split_name("pizero"),
+            ("pizero".to_string(), None)
+        );
+    }
+
+    /// Тесты, меняющие переменные окружения и корневой каталог, выполняются по одному
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Переменные окружения теста выбора корневого каталога
+    ///
+    /// Исходные значения и сброс аргумента командной строки восстанавливаются при удалении
+    struct EnvGuard {
+        saved: Vec<(&'static str, Option<OsString>)>,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl EnvGuard {
+        fn new(vars: &[(&'static str, Option<&Path>)]) -> Self {
+            let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let saved = vars
+                .iter()
+                .map(|(name, _)| (*name, std::env::var_os(name)))
+                .collect();
+            for (name, value) in vars {
+                set_env(name, *value);
+            }
+            Self { saved, _lock: lock }
+        }
+    }
+
+    impl Drop for EnvGuard {
+        fn drop(&mut self) {
+            *CONFIG_ROOT_OVERRIDE
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = None;
+            for (name, value) in &self.saved {
+                set_env(name, value.as_deref().map(Path::new));
+            }
+        }
+    }
+
+    fn set_env(name: &str, value: Option<&Path>) {
+        // SAFETY: переменные окружения меняются только под ENV_LOCK
+        unsafe {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_root_flag_over_env() {
+        let dir = temp_dir("root-flag");
+        let _env = EnvGuard::new(&[(CONFIG_ROOT_ENV, Some(&dir.join("env")))]);
+
+        set_config_root(dir.join("flag"));
+        assert_eq!(config_root(), dir.join("flag"));
+    }
+
+    #[test]
+    fn test_root_env_over_xdg() {
+        let dir = temp_dir("root-env");
+        fs::create_dir_all(dir.join(CONFIG_ROOT_NAME)).unwrap();
+        let _env = EnvGuard::new(&[
+            (CONFIG_ROOT_ENV, Some(&dir.join("env"))),
+            ("XDG_CONFIG_HOME", Some(&dir)),
+        ]);
+
+        assert_eq!(config_root(), dir.join("env"));
+    }
+
+    #[test]
+    fn test_root_xdg_over_system() {
+        let dir = temp_dir("root-xdg");
+        let xdg_root = dir.join(CONFIG_ROOT_NAME);
+        let system_root = dir.join("etc");
+        fs::create_dir_all(&xdg_root).unwrap();
+        fs::create_dir_all(&system_root).unwrap();
+        let _env = EnvGuard::new(&[(CONFIG_ROOT_ENV, None), ("XDG_CONFIG_HOME", Some(&dir))]);
+
+        assert_eq!(select_root(&system_root), xdg_root);
+    }
+
+    #[test]
+    fn test_root_system_fallback() {
+        let dir = temp_dir("root-system");
+        let system_root = dir.join("etc");
+        fs::create_dir_all(&system_root).unwrap();
+        let _env = EnvGuard::new(&[(CONFIG_ROOT_ENV, None), ("XDG_CONFIG_HOME", Some(&dir))]);
+
+        // Каталога XDG нет - используется системный каталог
+        assert_eq!(select_root(&system_root), system_root);
+
+        // Ни одного каталога нет - каталог XDG для первого сохранения
+        fs::remove_dir(&system_root).unwrap();
+        assert_eq!(select_root(&system_root), dir.join(CONFIG_ROOT_NAME));
+    }
+
+    #[test]
+    fn test_migrate_v0_ini() {
+        let root = temp_dir("migrate");
+        let _env = EnvGuard::new(&[]);
+        set_config_root(&root);
+
+        let serial = "[serial_settings]\nport_name=/dev/ttyUSB0\nbaud_rate=19200\n";
+        let serial_path = root.join("serial").join("old.ini");
+        fs::create_dir_all(root.join("serial")).unwrap();
+        fs::write(&serial_path, serial).unwrap();
+
+        let config = PortConfig::create_from_existing("old").unwrap();
+        assert_eq!(config.get_port_name(), "/dev/ttyUSB0");
+        assert_eq!(config.get_baud_rate(), 19200);
+        assert_eq!(
+            config.get_response_timeout(),
+            ResponseTimeouts::default().response
+        );
+        assert_eq!(config.get_checksum(), Checksum::Crc8);
+        assert_eq!(
+            config.get_handshake_retry(),
+            HandshakePolicy::default().retry
+        );
+        assert_eq!(config.get_heartbeat(), None);
+
+        // Исходный файл сохранен рядом, обновленный содержит все ключи текущей версии
+        let backup = root.join("serial").join("old.ini.v0.bak");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), serial);
+        let migrated = fs::read_to_string(&serial_path).unwrap();
+        assert!(migrated.contains("version=1"));
+        assert!(migrated.contains("port_name=/dev/ttyUSB0"));
+        assert!(migrated.contains("attempts=3"));
+
+        // Файл текущей версии загружается без перезаписи
+        fs::remove_file(&backup).unwrap();
+        PortConfig::create_from_existing("old").unwrap();
+        assert!(!backup.exists());
+        assert_eq!(fs::read_to_string(&serial_path).unwrap(), migrated);
+
+        let device = "[device_settings]\ngroup_number=5\nmusic_volume_idx=1\nsound_volume_idx=2\nload_capacity_idx=3\n";
+        fs::create_dir_all(root.join("device")).unwrap();
+        fs::write(root.join("device").join("old.ini"), device).unwrap();
+
+        let config = DeviceConfig::create_from_existing("old").unwrap();
+        assert_eq!(config.get_value("groupnumber"), Some(5));
+        assert_eq!(config.get_value("periodicity"), Some(PERIODICITY.default));
+        assert_eq!(
+            fs::read_to_string(root.join("device").join("old.ini.v0.bak")).unwrap(),
+            device
+        );
+        let migrated = fs::read_to_string(root.join("device").join("old.ini")).unwrap();
+        assert!(migrated.contains(&format!("periodicity={}", PERIODICITY.default)));
+    }
+
+    #[test]
+    fn test_reject_newer_schema() {
+        let root = temp_dir("newer-schema");
+        let _env = EnvGuard::new(&[]);
+        set_config_root(&root);
+
+        let device = r#"{ "schema": { "version": 99 }, "device_settings": { "group_number": 5 } }"#;
+        let path = root.join("device").join("new.json");
+        fs::create_dir_all(root.join("device")).unwrap();
+        fs::write(&path, device).unwrap();
+
+        let result = DeviceConfig::create_from_existing("new");
+        assert!(matches!(
+            result,
+            Err(ConfigError::SchemaVersion {
+                found: 99,
+                supported: 1
+            })
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), device);
+        assert!(!root.join("device").join("new.json.v99.bak").exists());
+    }
+}
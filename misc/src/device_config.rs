@@ -1,32 +1,35 @@
-use crate::config::ConfigIO;
-use configparser::ini::Ini;
-use std::{fmt::Display, fs};
-
-const MAX_GROUP_NUMBER: u8 = 15;
-const MAX_SOUND_VOLUME_IDX: u8 = 4;
-const MAX_MUSIC_VOLUME_IDX: u8 = 4;
-const MAX_CAPACITY_IDX: u8 = 15;
-
-pub const LOAD_PERSON_VARIANTS: [&str; 16] = [
-    "СКРЫТО",
-    "240кг 3чел.",
-    "320кг 4чел.",
-    "400кг 5чел.",
-    "450кг 6чел.",
-    "525кг 7чел.",
-    "630кг 8чел.",
-    "800кг 10чел.",
-    "800кг 11чел.",
-    "1000кг 13чел.",
-    "1150кг 15чел.",
-    "1275кг 16чел.",
-    "1275кг 17чел.",
-    "1425кг 18чел.",
-    "1600кг 20чел.",
-    "1600кг 21чел.",
-];
-
-// TODO: from, newtype pattern, tests
+use crate::config::{
+    ConfigDocument, ConfigError, ConfigFormat, ConfigIO, config_path, list_configs, load_migrated,
+    write_config,
+};
+use crate::migration::{Migration, Schema, fill_default};
+use crate::parameter::{self, PARAMETERS, ParameterSpec};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+/// Подкаталог конфигов устройства
+const CONFIG_DIR: &str = "device";
+/// Секция параметров устройства в файле конфига
+const SECTION: &str = "device_settings";
+
+/// Схема файла конфига устройства
+///
+/// Новый необязательный параметр добавляется в реестр вместе с шагом миграции
+/// `fill_optional`, заполняющим значения по умолчанию из реестра
+const SCHEMA: Schema = Schema {
+    migrations: &[Migration {
+        description: "fill optional parameters (PERIODICITY) defaults",
+        apply: fill_optional,
+    }],
+};
+
+/// Заполнение отсутствующих необязательных параметров значениями по умолчанию
+fn fill_optional(document: &mut ConfigDocument) {
+    for spec in PARAMETERS.iter().filter(|spec| spec.optional) {
+        fill_default(document, SECTION, spec.ini_key, spec.default);
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct GroupNumber(pub u8);
@@ -36,109 +39,82 @@ pub struct MusicVolumeIdx(pub u8);
 pub struct SoundVolumeIdx(pub u8);
 #[derive(Debug, Clone)]
 pub struct LoadCapacityIdx(pub u8);
-
+/// Период отправки данных в режиме PeriodicMode, шагами по 100 мс
+/// (0 - период по умолчанию прошивки)
 #[derive(Debug, Clone)]
+pub struct Periodicity(pub u8);
+
+/// Значения параметров устройства в порядке `parameter::PARAMETERS`
+///
+/// В файле конфига - секция `[device_settings]` с ключами `ParameterSpec::ini_key`,
+/// имя конфига в документ не входит
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "DeviceDocument", try_from = "DeviceDocument")]
 pub struct DeviceConfig {
     config_name: String,
-    group_number: GroupNumber,
-    music_volume_idx: MusicVolumeIdx,
-    sound_volume_idx: SoundVolumeIdx,
-    load_capacity_idx: LoadCapacityIdx,
+    format: ConfigFormat,
+    values: [u8; PARAMETERS.len()],
 }
 
 impl DeviceConfig {
-    pub fn get_group_number(&self) -> GroupNumber {
-        self.group_number.clone()
-    }
-
-    pub fn get_music_volume_idx(&self) -> MusicVolumeIdx {
-        self.music_volume_idx.clone()
-    }
-
-    pub fn get_sound_volume_idx(&self) -> SoundVolumeIdx {
-        self.sound_volume_idx.clone()
+    /// Конфиг с параметрами по умолчанию без привязки к файлу
+    ///
+    /// Расширение в имени (`.ini`, `.toml`, `.json`) задает формат хранения
+    pub fn new(name: &str) -> Self {
+        let (config_name, format) = ConfigFormat::split_name(name);
+        Self {
+            config_name,
+            format: format.unwrap_or_default(),
+            values: PARAMETERS.map(|spec| spec.default),
+        }
     }
 
-    pub fn get_load_capacity_idx(&self) -> LoadCapacityIdx {
-        self.load_capacity_idx.clone()
+    /// Значение параметра по имени в протоколе
+    pub fn get_value(&self, key: &str) -> Option<u8> {
+        position(key).map(|idx| self.values[idx])
     }
 
-    pub fn set_group_number(&mut self, group_number: GroupNumber) -> Result<(), String> {
-        if group_number.0 > MAX_GROUP_NUMBER {
-            return Err(format!(
-                "Group number must be less than {}",
-                MAX_GROUP_NUMBER + 1
-            ));
-        }
-
-        self.group_number = group_number;
+    /// Запись значения параметра по имени в протоколе с проверкой диапазона
+    pub fn set_value(&mut self, key: &str, value: u8) -> Result<(), ConfigError> {
+        let idx = position(key).ok_or_else(|| ConfigError::UnknownParameter(key.to_string()))?;
+        PARAMETERS[idx].check(value)?;
+        self.values[idx] = value;
         Ok(())
     }
 
-    pub fn set_music_volume_idx(&mut self, music_volume_idx: MusicVolumeIdx) -> Result<(), String> {
-        if music_volume_idx.0 > MAX_MUSIC_VOLUME_IDX {
-            return Err(format!(
-                "Music volume index must be less than {}",
-                MAX_MUSIC_VOLUME_IDX + 1
-            ));
-        }
-
-        self.music_volume_idx = music_volume_idx;
-        Ok(())
+    pub fn get<P: DeviceParameter>(&self) -> P {
+        P::from_raw(self.get_value(P::SPEC.key).unwrap_or(P::SPEC.default))
     }
 
-    pub fn set_sound_volume_idx(&mut self, sound_volume_idx: SoundVolumeIdx) -> Result<(), String> {
-        if sound_volume_idx.0 > MAX_SOUND_VOLUME_IDX {
-            return Err(format!(
-                "Sound volume index must be less than {}",
-                MAX_SOUND_VOLUME_IDX + 1
-            ));
-        }
-
-        self.sound_volume_idx = sound_volume_idx;
-        Ok(())
+    pub fn set<P: DeviceParameter>(&mut self, parameter: P) -> Result<(), ConfigError> {
+        self.set_value(P::SPEC.key, parameter.raw())
     }
 
-    pub fn set_load_capacity_idx(
-        &mut self,
-        load_capacity_idx: LoadCapacityIdx,
-    ) -> Result<(), String> {
-        if load_capacity_idx.0 > MAX_CAPACITY_IDX {
-            return Err(format!(
-                "Load capacity index must be less than {}",
-                MAX_CAPACITY_IDX + 1
-            ));
-        }
-
-        self.load_capacity_idx = load_capacity_idx;
-        Ok(())
+    /// Пары (описание параметра, значение)
+    pub fn parameters(&self) -> impl Iterator<Item = (&'static ParameterSpec, u8)> + '_ {
+        PARAMETERS.iter().zip(self.values.iter().copied())
     }
 }
 
+/// Индекс параметра в реестре
+fn position(key: &str) -> Option<usize> {
+    PARAMETERS.iter().position(|spec| spec.key == key)
+}
+
 impl ConfigIO for DeviceConfig {
-    fn create_new(name: &str) -> Result<Self, String> {
-        let config = Self {
-            config_name: name.to_string(),
-            group_number: GroupNumber(0),
-            music_volume_idx: MusicVolumeIdx(0),
-            sound_volume_idx: SoundVolumeIdx(2),
-            load_capacity_idx: LoadCapacityIdx(0),
-        };
+    fn create_new(name: &str) -> Result<Self, ConfigError> {
+        let config = Self::new(name);
         config.save_parameters()?;
         Ok(config)
     }
 
-    fn create_from_existing(name: &str) -> Result<Self, String>
+    fn create_from_existing(name: &str) -> Result<Self, ConfigError>
     where
         Self: Sized,
     {
-        let mut config = Self {
-            config_name: name.to_string(),
-            group_number: GroupNumber(0),
-            music_volume_idx: MusicVolumeIdx(0),
-            sound_volume_idx: SoundVolumeIdx(2),
-            load_capacity_idx: LoadCapacityIdx(0),
-        };
+        let (name, format) = ConfigFormat::locate(CONFIG_DIR, name);
+        let mut config = Self::new(&name);
+        config.set_format(format);
         config.load_parameters()?;
         Ok(config)
     }
@@ -147,233 +123,127 @@ impl ConfigIO for DeviceConfig {
         self.config_name.clone()
     }
 
-    fn load_parameters(&mut self) -> Result<(), String> {
-        let mut config_instance = Ini::new();
-        config_instance.load(format!("configs/device/{}.ini", self.config_name))?;
-
-        match config_instance.getuint("device_settings", "GROUP_NUMBER") {
-            Ok(Some(group_number)) => self
-                .set_group_number(GroupNumber(group_number as u8))
-                .map_err(|e| e.to_string())?,
-            _ => return Err("Unable to get group number".to_string()),
-        };
-
-        match config_instance.getuint("device_settings", "MUSIC_VOLUME_IDX") {
-            Ok(Some(music_volume_idx)) => self
-                .set_music_volume_idx(MusicVolumeIdx(music_volume_idx as u8))
-                .map_err(|e| e.to_string())?,
-            _ => return Err("Unable to get music volume index".to_string()),
-        };
-
-        match config_instance.getuint("device_settings", "SOUND_VOLUME_IDX") {
-            Ok(Some(sound_volume_idx)) => self
-                .set_sound_volume_idx(SoundVolumeIdx(sound_volume_idx as u8))
-                .map_err(|e| e.to_string())?,
-            _ => return Err("Unable to get sound volume index".to_string()),
-        };
-
-        match config_instance.getuint("device_settings", "LOAD_CAPACITY_IDX") {
-            Ok(Some(load_capacity_idx)) => self
-                .set_load_capacity_idx(LoadCapacityIdx(load_capacity_idx as u8))
-                .map_err(|e| e.to_string())?,
-            _ => return Err("Unable to get load capacity index".to_string()),
-        };
-
-        Ok(())
+    fn get_format(&self) -> ConfigFormat {
+        self.format
     }
 
-    fn save_parameters(&self) -> Result<(), String> {
-        let mut config_instance = Ini::new();
-
-        config_instance.set(
-            "device_settings",
-            "GROUP_NUMBER",
-            Some(self.get_group_number().0.to_string()),
-        );
-        config_instance.set(
-            "device_settings",
-            "MUSIC_VOLUME_IDX",
-            Some(self.get_music_volume_idx().0.to_string()),
-        );
-        config_instance.set(
-            "device_settings",
-            "SOUND_VOLUME_IDX",
-            Some(self.get_sound_volume_idx().0.to_string()),
-        );
-        config_instance.set(
-            "device_settings",
-            "LOAD_CAPACITY_IDX",
-            Some(self.get_load_capacity_idx().0.to_string()),
-        );
-
-        return config_instance
-            .write(format!("configs/device/{}.ini", self.config_name))
-            .map_err(|e| e.to_string());
+    fn set_format(&mut self, format: ConfigFormat) {
+        self.format = format;
     }
-    fn list_existing_configs() -> Result<Vec<String>, String> {
-        let mut list_of_files = Vec::new();
-
-        if let Ok(entries) = fs::read_dir("configs/device/") {
-            for entry in entries {
-                if let Ok(dir) = entry {
-                    list_of_files.push(
-                        dir.path()
-                            .display()
-                            .to_string()
-                            .replace("configs/device/", "")
-                            .replace(".ini", ""),
-                    );
-                } else {
-                    break;
-                }
-            }
-            return Ok(list_of_files);
-        }
 
-        return Err("Unable to get config file names".to_string());
+    fn load_parameters(&mut self) -> Result<(), ConfigError> {
+        let path = config_path(CONFIG_DIR, &self.config_name, self.format);
+        let loaded: Self = load_migrated(&path, self.format, &SCHEMA, &Self::new(""))?;
+        self.values = loaded.values;
+        Ok(())
     }
-}
 
-impl Display for DeviceConfig {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "\n Config_name: {}.ini \n Group number: {}, \n Music volume index: {}, \n Sound volume index: {}, \n Load capacity index: {}",
-            self.config_name,
-            self.group_number.0,
-            self.music_volume_idx.0,
-            self.sound_volume_idx.0,
-            self.load_capacity_idx.0
-        )
+    fn save_parameters(&self) -> Result<(), ConfigError> {
+        let path = config_path(CONFIG_DIR, &self.config_name, self.format);
+        write_config(self, &path, self.format, &SCHEMA)
     }
-}
-
-impl TryFrom<String> for GroupNumber {
-    type Error = String;
-
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        let tokens = value.split(':').collect::<Vec<&str>>().to_vec();
-
-        if tokens.len() != 2 {
-            return Err("Unable to parse group number".to_string());
-        }
 
-        if tokens[0] != "groupnumber" {
-            return Err("Unable to parse group number".to_string());
-        }
-
-        let group_number = tokens[1].trim().parse::<u8>().map_err(|e| e.to_string())?;
-        Ok(GroupNumber(group_number))
+    fn list_existing_configs() -> Result<Vec<String>, ConfigError> {
+        list_configs(CONFIG_DIR)
     }
 }
 
-impl TryInto<String> for GroupNumber {
-    type Error = String;
+/// Документ конфига устройства: секция `[device_settings]`
+#[derive(Serialize, Deserialize)]
+struct DeviceDocument {
+    device_settings: BTreeMap<String, u64>,
+}
 
-    fn try_into(self) -> Result<String, Self::Error> {
-        Ok(format!("groupnumber {}", self.0))
+impl From<DeviceConfig> for DeviceDocument {
+    fn from(config: DeviceConfig) -> Self {
+        let device_settings = config
+            .parameters()
+            .map(|(spec, value)| (spec.ini_key.to_string(), u64::from(value)))
+            .collect();
+        Self { device_settings }
     }
 }
 
-impl TryFrom<String> for SoundVolumeIdx {
-    type Error = String;
+impl TryFrom<DeviceDocument> for DeviceConfig {
+    type Error = ConfigError;
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        let tokens = value.split(':').collect::<Vec<&str>>().to_vec();
-
-        if tokens.len() != 2 {
-            return Err("Unable to parse soundvolume number".to_string());
-        }
-
-        if tokens[0] != "soundvolume" {
-            return Err("Unable to parse soundvolume number".to_string());
+    fn try_from(document: DeviceDocument) -> Result<Self, Self::Error> {
+        let mut config = Self::new("");
+        for spec in &PARAMETERS {
+            // Необязательные параметры в старых файлах могут отсутствовать
+            let value = match document.device_settings.get(spec.ini_key) {
+                Some(value) => *value,
+                None if spec.optional => u64::from(spec.default),
+                None => {
+                    return Err(ConfigError::MissingKey {
+                        section: SECTION.to_string(),
+                        key: spec.ini_key.to_string(),
+                    });
+                }
+            };
+            config.set_value(spec.key, clamp_index(value))?;
         }
-
-        let sound_idx = tokens[1].trim().parse::<u8>().map_err(|e| e.to_string())?;
-        Ok(SoundVolumeIdx(sound_idx))
+        Ok(config)
     }
 }
 
-impl TryInto<String> for SoundVolumeIdx {
-    type Error = String;
-
-    fn try_into(self) -> Result<String, Self::Error> {
-        Ok(format!("soundvolume {}", self.0))
-    }
+/// Приведение значения из файла к индексу (с сохранением выхода за диапазон)
+fn clamp_index(value: u64) -> u8 {
+    u8::try_from(value).unwrap_or(u8::MAX)
 }
-impl TryFrom<String> for MusicVolumeIdx {
-    type Error = String;
-
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        let tokens = value.split(':').collect::<Vec<&str>>().to_vec();
 
-        if tokens.len() != 2 {
-            return Err("Unable to parse musicvolume number".to_string());
-        }
-
-        if tokens[0] != "musicvolume" {
-            return Err("Unable to parse musicvolume number".to_string());
+impl Display for DeviceConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\n Config_name: {}.{}", self.config_name, self.format)?;
+        for (spec, value) in self.parameters() {
+            write!(f, " \n {}: {}", spec.label, spec.display(value))?;
         }
-
-        let music_idx = tokens[1].trim().parse::<u8>().map_err(|e| e.to_string())?;
-        Ok(MusicVolumeIdx(music_idx))
-    }
-}
-
-impl TryInto<String> for MusicVolumeIdx {
-    type Error = String;
-
-    fn try_into(self) -> Result<String, Self::Error> {
-        Ok(format!("musicvolume {}", self.0))
+        Ok(())
     }
 }
-impl TryFrom<String> for LoadCapacityIdx {
-    type Error = String;
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        let tokens = value.split(':').collect::<Vec<&str>>().to_vec();
+/// Параметр устройства, передаваемый по протоколу
+///
+/// `SPEC` - описание параметра в реестре, `KEY` - имя параметра в командах `get`/`set`
+pub trait DeviceParameter: Sized {
+    const SPEC: ParameterSpec;
+    const KEY: &'static str = Self::SPEC.key;
 
-        if tokens.len() != 2 {
-            return Err("Unable to parse loadcapacity number".to_string());
-        }
-
-        if tokens[0] != "loadcapacity" {
-            return Err("Unable to parse loadcapacity number".to_string());
-        }
+    /// Значение параметра для передачи на устройство
+    fn raw(&self) -> u8;
 
-        let load_idx = tokens[1].trim().parse::<u8>().map_err(|e| e.to_string())?;
-        Ok(LoadCapacityIdx(load_idx))
-    }
+    /// Параметр из значения, полученного от устройства
+    fn from_raw(value: u8) -> Self;
 }
 
-impl TryInto<String> for LoadCapacityIdx {
-    type Error = String;
+macro_rules! device_parameter {
+    ($($name:ident => $spec:ident),* $(,)?) => {
+        $(
+            impl DeviceParameter for $name {
+                const SPEC: ParameterSpec = parameter::$spec;
 
-    fn try_into(self) -> Result<String, Self::Error> {
-        Ok(format!("loadcapacity {}", self.0))
-    }
-}
-
-impl Display for GroupNumber {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
+                fn raw(&self) -> u8 {
+                    self.0
+                }
 
-impl Display for MusicVolumeIdx {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} %", self.0 * 25)
-    }
-}
+                fn from_raw(value: u8) -> Self {
+                    Self(value)
+                }
+            }
 
-impl Display for SoundVolumeIdx {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} %", self.0 * 25)
-    }
+            impl Display for $name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "{}", Self::SPEC.display(self.0))
+                }
+            }
+        )*
+    };
 }
 
-impl Display for LoadCapacityIdx {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", LOAD_PERSON_VARIANTS[self.0 as usize])
-    }
+device_parameter! {
+    GroupNumber => GROUP_NUMBER,
+    MusicVolumeIdx => MUSIC_VOLUME,
+    SoundVolumeIdx => SOUND_VOLUME,
+    LoadCapacityIdx => LOAD_CAPACITY,
+    Periodicity => PERIODICITY,
 }
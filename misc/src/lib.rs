@@ -1,3 +1,5 @@
 pub mod config;
 pub mod device_config;
+pub mod migration;
+pub mod parameter;
 pub mod serial_config;
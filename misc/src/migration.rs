@@ -0,0 +1,149 @@
+use crate::config::{ConfigDocument, ConfigError};
+use log::debug;
+use serde_json::Value;
+
+/// Секция и ключ версии схемы файла конфига
+const SCHEMA_SECTION: &str = "schema";
+const VERSION_KEY: &str = "version";
+
+/// Шаг миграции файла конфига на следующую версию схемы
+pub struct Migration {
+    /// Описание изменений для журнала
+    pub description: &'static str,
+    pub apply: fn(&mut ConfigDocument),
+}
+
+/// Схема файла конфига с цепочкой миграций
+///
+/// Шаг `migrations[i]` переводит файл с версии `i` на версию `i + 1`,
+/// текущая версия равна длине цепочки. Файлы без версии имеют версию 0
+pub struct Schema {
+    pub migrations: &'static [Migration],
+}
+
+impl Schema {
+    /// Текущая версия схемы
+    pub fn version(&self) -> u64 {
+        self.migrations.len() as u64
+    }
+
+    /// Запись текущей версии схемы в документ
+    pub(crate) fn stamp(&self, document: &mut ConfigDocument) {
+        document.set(SCHEMA_SECTION, VERSION_KEY, self.version());
+    }
+
+    /// Приведение документа к текущей версии схемы
+    ///
+    /// Возвращает исходную версию, если документ был изменен
+    pub(crate) fn upgrade(
+        &self,
+        document: &mut ConfigDocument,
+    ) -> Result<Option<u64>, ConfigError> {
+        let found = match document.get(SCHEMA_SECTION, VERSION_KEY) {
+            None => 0,
+            Some(value) => value.as_u64().ok_or_else(|| ConfigError::InvalidValue {
+                section: SCHEMA_SECTION.to_string(),
+                key: VERSION_KEY.to_string(),
+                value: value.to_string(),
+            })?,
+        };
+        if found > self.version() {
+            return Err(ConfigError::SchemaVersion {
+                found,
+                supported: self.version(),
+            });
+        }
+        if found == self.version() {
+            return Ok(None);
+        }
+
+        for migration in &self.migrations[found as usize..] {
+            debug!("Config migration: {}", migration.description);
+            (migration.apply)(document);
+        }
+        self.stamp(document);
+
+        Ok(Some(found))
+    }
+}
+
+/// Добавление ключа со значением по умолчанию, если ключ отсутствует
+pub fn fill_default(
+    document: &mut ConfigDocument,
+    section: &str,
+    key: &str,
+    value: impl Into<Value>,
+) {
+    if document.get(section, key).is_none() {
+        document.set(section, key, value);
+    }
+}
+
+/// Добавление всех отсутствующих ключей документа `defaults`
+pub fn fill_defaults(document: &mut ConfigDocument, defaults: &ConfigDocument) {
+    for (section, key, value) in defaults.entries() {
+        fill_default(document, section, key, value.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: Schema = Schema {
+        migrations: &[
+            Migration {
+                description: "add timeout",
+                apply: |document| fill_default(document, "port", "timeout_ms", 100),
+            },
+            Migration {
+                description: "add mode",
+                apply: |document| fill_default(document, "port", "mode", "off"),
+            },
+        ],
+    };
+
+    #[test]
+    fn test_upgrade() {
+        let mut document = ConfigDocument::default();
+        document.set("port", "timeout_ms", 500);
+
+        assert_eq!(SCHEMA.upgrade(&mut document).unwrap(), Some(0));
+        // Существующее значение не перезаписывается
+        assert_eq!(document.get("port", "timeout_ms"), Some(&Value::from(500)));
+        assert_eq!(document.get("port", "mode"), Some(&Value::from("off")));
+        assert_eq!(document.get("schema", "version"), Some(&Value::from(2)));
+
+        assert_eq!(SCHEMA.upgrade(&mut document).unwrap(), None);
+    }
+
+    #[test]
+    fn test_upgrade_from_version() {
+        let mut document = ConfigDocument::default();
+        document.set("schema", "version", 1);
+
+        assert_eq!(SCHEMA.upgrade(&mut document).unwrap(), Some(1));
+        assert_eq!(document.get("port", "timeout_ms"), None);
+        assert_eq!(document.get("port", "mode"), Some(&Value::from("off")));
+    }
+
+    #[test]
+    fn test_newer_version() {
+        let mut document = ConfigDocument::default();
+        document.set("schema", "version", 3);
+
+        assert!(matches!(
+            SCHEMA.upgrade(&mut document),
+            Err(ConfigError::SchemaVersion {
+                found: 3,
+                supported: 2
+            })
+        ));
+
+        document.set("schema", "version", "one");
+        assert!(matches!(
+            SCHEMA.upgrade(&mut document),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+}
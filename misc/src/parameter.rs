@@ -0,0 +1,316 @@
+use crate::config::ConfigError;
+
+/// Шаг периода отправки данных, мс
+pub const PERIODICITY_STEP_MS: u64 = 100;
+/// Шаг громкости, %
+pub const VOLUME_STEP_PERCENT: u32 = 25;
+
+pub const LOAD_PERSON_VARIANTS: [&str; 16] = [
+    "СКРЫТО",
+    "240кг 3чел.",
+    "320кг 4чел.",
+    "400кг 5чел.",
+    "450кг 6чел.",
+    "525кг 7чел.",
+    "630кг 8чел.",
+    "800кг 10чел.",
+    "800кг 11чел.",
+    "1000кг 13чел.",
+    "1150кг 15чел.",
+    "1275кг 16чел.",
+    "1275кг 17чел.",
+    "1425кг 18чел.",
+    "1600кг 20чел.",
+    "1600кг 21чел.",
+];
+
+/// Допустимые значения параметра
+#[derive(Debug, Clone, Copy)]
+pub enum ParameterValues {
+    /// Числовой диапазон `0..=max` с форматированием значения для отображения
+    Range { max: u8, format: fn(u8) -> String },
+    /// Перечисление вариантов: значение параметра - индекс варианта
+    Choices(&'static [&'static str]),
+}
+
+/// Пункт меню индикатора (`rpi_menu`)
+#[derive(Debug, Clone, Copy)]
+pub struct MenuItem {
+    /// Позиция пункта в меню (не совпадает с порядком обмена с прошивкой)
+    pub order: u8,
+    /// Вариант значения в списке выбора (`None` - как в `ParameterSpec::display`)
+    pub choice: Option<fn(u8) -> String>,
+}
+
+/// Описание параметра устройства
+///
+/// По описанию параметр читается и пишется в файл конфига,
+/// передается по протоколу и отображается в меню
+#[derive(Debug, Clone, Copy)]
+pub struct ParameterSpec {
+    /// Название в меню индикатора
+    pub title: &'static str,
+    /// Название в сообщениях об ошибках и выводе утилит
+    pub label: &'static str,
+    /// Имя параметра в командах `get`/`set`
+    pub key: &'static str,
+    /// Ключ в секции `[device_settings]` файла конфига (одинаковый во всех форматах)
+    pub ini_key: &'static str,
+    pub values: ParameterValues,
+    pub default: u8,
+    /// Параметр появился позже остальных: может отсутствовать
+    /// в старых файлах конфига и не поддерживаться старыми прошивками
+    pub optional: bool,
+    /// Пункт меню индикатора (`None` - параметр в меню не показывается)
+    pub menu: Option<MenuItem>,
+}
+
+impl ParameterSpec {
+    /// Максимальное допустимое значение
+    pub fn max(&self) -> u8 {
+        match self.values {
+            ParameterValues::Range { max, .. } => max,
+            ParameterValues::Choices(choices) => (choices.len() - 1) as u8,
+        }
+    }
+
+    /// Проверка значения на вхождение в допустимый диапазон
+    pub fn check(&self, value: u8) -> Result<(), ConfigError> {
+        if value > self.max() {
+            return Err(ConfigError::OutOfRange {
+                parameter: self.label,
+                value: u32::from(value),
+                max: u32::from(self.max()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Значение в виде для отображения пользователю
+    pub fn display(&self, value: u8) -> String {
+        match self.values {
+            ParameterValues::Range { format, .. } => format(value),
+            ParameterValues::Choices(choices) => choices
+                .get(value as usize)
+                .map_or_else(|| value.to_string(), |choice| choice.to_string()),
+        }
+    }
+
+    /// Все допустимые значения в виде для отображения (индекс - значение)
+    pub fn choices(&self) -> Vec<String> {
+        (0..=self.max()).map(|value| self.display(value)).collect()
+    }
+
+    /// Все допустимые значения в виде для списка выбора меню (индекс - значение)
+    pub fn menu_choices(&self) -> Vec<String> {
+        match self.menu.and_then(|item| item.choice) {
+            Some(choice) => (0..=self.max()).map(choice).collect(),
+            None => self.choices(),
+        }
+    }
+}
+
+fn format_number(value: u8) -> String {
+    value.to_string()
+}
+
+fn format_volume(value: u8) -> String {
+    format!("{} %", u32::from(value) * VOLUME_STEP_PERCENT)
+}
+
+/// Громкость в списке выбора меню - без пробела перед `%`
+fn format_volume_choice(value: u8) -> String {
+    format!("{}%", u32::from(value) * VOLUME_STEP_PERCENT)
+}
+
+fn format_periodicity(value: u8) -> String {
+    match value {
+        0 => "firmware default".to_string(),
+        value => format!("{} ms", u64::from(value) * PERIODICITY_STEP_MS),
+    }
+}
+
+pub const GROUP_NUMBER: ParameterSpec = ParameterSpec {
+    title: "Номер лифта в группе",
+    label: "Group number",
+    key: "groupnumber",
+    ini_key: "group_number",
+    values: ParameterValues::Range {
+        max: 15,
+        format: format_number,
+    },
+    default: 0,
+    optional: false,
+    menu: Some(MenuItem {
+        order: 0,
+        choice: None,
+    }),
+};
+
+pub const SOUND_VOLUME: ParameterSpec = ParameterSpec {
+    title: "Громкость звукового сопровождения",
+    label: "Sound volume",
+    key: "soundvolume",
+    ini_key: "sound_volume_idx",
+    values: ParameterValues::Range {
+        max: 4,
+        format: format_volume,
+    },
+    default: 2,
+    optional: false,
+    menu: Some(MenuItem {
+        order: 1,
+        choice: Some(format_volume_choice),
+    }),
+};
+
+pub const MUSIC_VOLUME: ParameterSpec = ParameterSpec {
+    title: "Громкость музыкального сопровождения",
+    label: "Music volume",
+    key: "musicvolume",
+    ini_key: "music_volume_idx",
+    values: ParameterValues::Range {
+        max: 4,
+        format: format_volume,
+    },
+    default: 0,
+    optional: false,
+    menu: Some(MenuItem {
+        order: 2,
+        choice: Some(format_volume_choice),
+    }),
+};
+
+pub const LOAD_CAPACITY: ParameterSpec = ParameterSpec {
+    title: "Грузоподъемность лифта",
+    label: "Load capacity",
+    key: "loadcapacity",
+    ini_key: "load_capacity_idx",
+    values: ParameterValues::Choices(&LOAD_PERSON_VARIANTS),
+    default: 0,
+    optional: false,
+    menu: Some(MenuItem {
+        order: 3,
+        choice: None,
+    }),
+};
+
+/// Период отправки данных в режиме PeriodicMode, шагами по 100 мс
+/// (0 - период по умолчанию прошивки)
+pub const PERIODICITY: ParameterSpec = ParameterSpec {
+    title: "Период отправки данных",
+    label: "Periodicity",
+    key: "periodicity",
+    ini_key: "periodicity",
+    values: ParameterValues::Range {
+        max: 100,
+        format: format_periodicity,
+    },
+    // 1 с
+    default: 10,
+    optional: true,
+    menu: None,
+};
+
+/// Все параметры устройства в порядке обмена с прошивкой
+pub const PARAMETERS: [ParameterSpec; 5] = [
+    GROUP_NUMBER,
+    MUSIC_VOLUME,
+    SOUND_VOLUME,
+    LOAD_CAPACITY,
+    PERIODICITY,
+];
+
+/// Параметры, показываемые в меню индикатора, в порядке пунктов меню
+pub fn menu_parameters() -> Vec<&'static ParameterSpec> {
+    let mut parameters: Vec<_> = PARAMETERS
+        .iter()
+        .filter_map(|spec| spec.menu.map(|item| (item.order, spec)))
+        .collect();
+    parameters.sort_by_key(|(order, _)| *order);
+    parameters.into_iter().map(|(_, spec)| spec).collect()
+}
+
+/// Описание параметра по имени в протоколе
+pub fn find(key: &str) -> Option<&'static ParameterSpec> {
+    PARAMETERS.iter().find(|spec| spec.key == key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_boundary() {
+        assert!(GROUP_NUMBER.check(15).is_ok());
+        assert!(matches!(
+            GROUP_NUMBER.check(16),
+            Err(ConfigError::OutOfRange {
+                parameter: "Group number",
+                value: 16,
+                max: 15
+            })
+        ));
+
+        // Граница перечисления - последний вариант
+        assert_eq!(LOAD_CAPACITY.max(), 15);
+        assert!(LOAD_CAPACITY.check(15).is_ok());
+        assert!(LOAD_CAPACITY.check(16).is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(SOUND_VOLUME.display(1), "25 %");
+        assert_eq!(PERIODICITY.display(0), "firmware default");
+        assert_eq!(PERIODICITY.display(10), "1000 ms");
+        assert_eq!(LOAD_CAPACITY.display(2), "320кг 4чел.");
+        // Значение вне перечисления выводится числом
+        assert_eq!(LOAD_CAPACITY.display(20), "20");
+    }
+
+    #[test]
+    fn test_choices() {
+        assert_eq!(
+            MUSIC_VOLUME.choices(),
+            vec!["0 %", "25 %", "50 %", "75 %", "100 %"]
+        );
+        assert_eq!(LOAD_CAPACITY.choices(), LOAD_PERSON_VARIANTS.to_vec());
+        assert_eq!(GROUP_NUMBER.choices().len(), 16);
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(
+            find("soundvolume").map(|spec| spec.ini_key),
+            Some("sound_volume_idx")
+        );
+        assert!(find("sound_volume_idx").is_none());
+        assert!(find("mode").is_none());
+
+        for spec in &PARAMETERS {
+            assert!(find(spec.key).is_some_and(|found| found.ini_key == spec.ini_key));
+            assert!(spec.check(spec.default).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_menu_parameters() {
+        let keys: Vec<_> = menu_parameters().iter().map(|spec| spec.key).collect();
+        assert_eq!(
+            keys,
+            vec![
+                GROUP_NUMBER.key,
+                SOUND_VOLUME.key,
+                MUSIC_VOLUME.key,
+                LOAD_CAPACITY.key
+            ]
+        );
+
+        assert_eq!(
+            SOUND_VOLUME.menu_choices(),
+            vec!["0%", "25%", "50%", "75%", "100%"]
+        );
+        // Без отдельного формата список выбора совпадает с отображением
+        assert_eq!(LOAD_CAPACITY.menu_choices(), LOAD_CAPACITY.choices());
+    }
+}
@@ -1,42 +1,236 @@
-use crate::config::ConfigIO;
-use configparser::ini::Ini;
-use std::{fmt::Display, fs};
+use crate::config::{
+    ConfigDocument, ConfigError, ConfigFormat, ConfigIO, config_path, list_configs, load_migrated,
+    millis, optional_millis, write_config,
+};
+use crate::migration::{Migration, Schema, fill_defaults};
+use protocol::checksum::Checksum;
+use protocol::options::{
+    DEFAULT_HANDSHAKE_REPLY, HandshakePolicy, HeartbeatMode, HeartbeatPolicy, ResponseTimeouts,
+    RetryPolicy,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{fmt::Display, time::Duration};
 
-// TODO: TIMEOUT
-// TODO: from, newtype pattern, tests
+/// Подкаталог конфигов порта
+const CONFIG_DIR: &str = "serial";
 
-#[derive(Debug, Clone)]
-pub struct PortConfig {
-    config_name: String,
+/// Схема файла конфига порта
+///
+/// Первая версия файла содержала только порт и скорость, остальные
+/// ключи заполняются значениями конфига по умолчанию
+const SCHEMA: Schema = Schema {
+    migrations: &[Migration {
+        description: "fill timeouts, checksum, handshake, retry and heartbeat defaults",
+        apply: |document| {
+            let defaults = ConfigDocument::of(&PortConfig::new(""), &SCHEMA)
+                .expect("default port config is serializable");
+            fill_defaults(document, &defaults);
+        },
+    }],
+};
+
+/// Значение `mode` секции `[heartbeat]`, отключающее контроль связи
+const HEARTBEAT_OFF: &str = "off";
+
+/// Секция `[serial_settings]`: порт, таймауты ответа и контрольная сумма
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SerialSettings {
     port_name: String,
     baud_rate: u32,
+    #[serde(rename = "response_timeout_ms", with = "millis")]
+    response_timeout: Duration,
+    #[serde(rename = "inter_byte_timeout_ms", with = "millis")]
+    inter_byte_timeout: Duration,
+    #[serde(with = "checksum_name")]
+    checksum: Checksum,
+}
+
+/// Секция `[handshake]`: приветствие, сведения об устройстве и повторы приветствия
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct HandshakeSettings {
+    request: String,
+    reply: String,
+    query_info: bool,
+    sequence_ids: bool,
+    /// Пустая строка - оставить контрольную сумму подключения
+    #[serde(with = "optional_checksum_name")]
+    checksum: Option<Checksum>,
+    #[serde(flatten, with = "retry_policy")]
+    retry: RetryPolicy,
+}
+
+/// Настройки порта
+///
+/// Поля секций - ключи файла конфига во всех форматах,
+/// имя конфига в документ не входит
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortConfig {
+    #[serde(skip)]
+    config_name: String,
+    #[serde(skip)]
+    format: ConfigFormat,
+    serial_settings: SerialSettings,
+    handshake: HandshakeSettings,
+    /// Повторы запросов, оставшихся без ответа
+    #[serde(with = "retry_policy")]
+    retry: RetryPolicy,
+    /// Контроль связи (`None` - отключен)
+    #[serde(with = "heartbeat_policy")]
+    heartbeat: Option<HeartbeatPolicy>,
 }
 
 impl PortConfig {
+    /// Конфиг с параметрами по умолчанию без привязки к файлу
+    ///
+    /// Значения по умолчанию - настройки клиента протокола по умолчанию.
+    /// Расширение в имени (`.ini`, `.toml`, `.json`) задает формат хранения
+    pub fn new(name: &str) -> Self {
+        let (config_name, format) = ConfigFormat::split_name(name);
+        let timeouts = ResponseTimeouts::default();
+        let handshake = HandshakePolicy::default();
+        Self {
+            config_name,
+            format: format.unwrap_or_default(),
+            serial_settings: SerialSettings {
+                port_name: "/dev/ttyAMA0".to_string(),
+                baud_rate: 9600,
+                response_timeout: timeouts.response,
+                inter_byte_timeout: timeouts.inter_byte,
+                checksum: Checksum::default(),
+            },
+            handshake: HandshakeSettings {
+                request: handshake.request,
+                reply: DEFAULT_HANDSHAKE_REPLY.to_string(),
+                query_info: handshake.query_info,
+                sequence_ids: handshake.sequence_ids,
+                checksum: handshake.checksum,
+                retry: handshake.retry,
+            },
+            retry: RetryPolicy::none(),
+            heartbeat: None,
+        }
+    }
+
     pub fn get_port_name(&self) -> String {
-        self.port_name.clone()
+        self.serial_settings.port_name.clone()
     }
 
     pub fn get_baud_rate(&self) -> u32 {
-        self.baud_rate
+        self.serial_settings.baud_rate
     }
 
     pub fn set_port_name(&mut self, port_name: String) {
-        self.port_name = port_name;
+        self.serial_settings.port_name = port_name;
     }
 
     pub fn set_baud_rate(&mut self, baud_rate: u32) {
-        self.baud_rate = baud_rate;
+        self.serial_settings.baud_rate = baud_rate;
+    }
+
+    /// Крайний срок получения ответа устройства
+    pub fn get_response_timeout(&self) -> Duration {
+        self.serial_settings.response_timeout
+    }
+
+    /// Допустимая пауза между байтами ответа
+    pub fn get_inter_byte_timeout(&self) -> Duration {
+        self.serial_settings.inter_byte_timeout
+    }
+
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.serial_settings.response_timeout = timeout;
+    }
+
+    pub fn set_inter_byte_timeout(&mut self, timeout: Duration) {
+        self.serial_settings.inter_byte_timeout = timeout;
+    }
+
+    /// Контрольная сумма пакетов при подключении
+    pub fn get_checksum(&self) -> Checksum {
+        self.serial_settings.checksum
+    }
+
+    pub fn set_checksum(&mut self, checksum: Checksum) {
+        self.serial_settings.checksum = checksum;
+    }
+
+    /// Контрольная сумма, на которую следует перейти после подключения
+    ///
+    /// `None` - оставить контрольную сумму подключения
+    pub fn get_handshake_checksum(&self) -> Option<Checksum> {
+        self.handshake.checksum
+    }
+
+    pub fn set_handshake_checksum(&mut self, checksum: Option<Checksum>) {
+        self.handshake.checksum = checksum;
+    }
+
+    /// Запрос приветствия при подключении
+    pub fn get_handshake_request(&self) -> String {
+        self.handshake.request.clone()
+    }
+
+    /// Ожидаемый ответ на приветствие (`exact:`, `prefix:`, `contains:`, варианты через `|`)
+    pub fn get_handshake_reply(&self) -> String {
+        self.handshake.reply.clone()
+    }
+
+    pub fn get_handshake_retry(&self) -> RetryPolicy {
+        self.handshake.retry.clone()
+    }
+
+    /// Повторы запросов, оставшихся без ответа
+    pub fn get_request_retry(&self) -> RetryPolicy {
+        self.retry.clone()
+    }
+
+    /// Запрос сведений об устройстве после приветствия
+    pub fn get_query_device_info(&self) -> bool {
+        self.handshake.query_info
+    }
+
+    pub fn set_handshake_request(&mut self, request: String) {
+        self.handshake.request = request;
+    }
+
+    pub fn set_handshake_reply(&mut self, reply: String) {
+        self.handshake.reply = reply;
+    }
+
+    pub fn set_handshake_retry(&mut self, retry: RetryPolicy) {
+        self.handshake.retry = retry;
+    }
+
+    pub fn set_request_retry(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    pub fn set_query_device_info(&mut self, query: bool) {
+        self.handshake.query_info = query;
+    }
+
+    /// Нумерация запросов, если устройство ее поддерживает
+    pub fn get_sequence_ids(&self) -> bool {
+        self.handshake.sequence_ids
+    }
+
+    pub fn set_sequence_ids(&mut self, enabled: bool) {
+        self.handshake.sequence_ids = enabled;
+    }
+
+    /// Контроль связи и переподключение (`None` - контроль отключен)
+    pub fn get_heartbeat(&self) -> Option<HeartbeatPolicy> {
+        self.heartbeat.clone()
+    }
+
+    pub fn set_heartbeat(&mut self, heartbeat: Option<HeartbeatPolicy>) {
+        self.heartbeat = heartbeat;
     }
 }
 
 impl ConfigIO for PortConfig {
-    fn create_new(name: &str) -> Result<Self, String> {
-        let config = Self {
-            config_name: name.to_string(),
-            port_name: "/dev/ttyAMA0".to_string(),
-            baud_rate: 9600,
-        };
+    fn create_new(name: &str) -> Result<Self, ConfigError> {
+        let config = Self::new(name);
         //config.save_parameters()?;
         Ok(config)
     }
@@ -45,71 +239,43 @@ impl ConfigIO for PortConfig {
         self.config_name.clone()
     }
 
-    fn create_from_existing(name: &str) -> Result<Self, String>
+    fn create_from_existing(name: &str) -> Result<Self, ConfigError>
     where
         Self: Sized,
     {
-        let mut config = Self {
-            config_name: name.to_string(),
-            port_name: "/dev/ttyAMA0".to_string(),
-            baud_rate: 9600,
-        };
+        let (name, format) = ConfigFormat::locate(CONFIG_DIR, name);
+        let mut config = Self::new(&name);
+        config.set_format(format);
         config.load_parameters()?;
         Ok(config)
     }
 
-    fn load_parameters(&mut self) -> Result<(), String> {
-        let mut config_instance = Ini::new();
-        config_instance.load(format!("configs/serial/{}.ini", self.config_name))?;
+    fn get_format(&self) -> ConfigFormat {
+        self.format
+    }
 
-        match config_instance.get("serial_settings", "PORT_NAME") {
-            Some(name) => self.set_port_name(name),
-            None => return Err("Unable to get port name".to_string()),
-        };
+    fn set_format(&mut self, format: ConfigFormat) {
+        self.format = format;
+    }
 
-        match config_instance.getuint("serial_settings", "BAUD_RATE") {
-            Ok(Some(baud_rate)) => self.set_baud_rate(baud_rate as u32),
-            _ => return Err("Unable to get baud rate".to_string()),
+    fn load_parameters(&mut self) -> Result<(), ConfigError> {
+        let path = config_path(CONFIG_DIR, &self.config_name, self.format);
+        let loaded: Self = load_migrated(&path, self.format, &SCHEMA, &Self::new(""))?;
+        *self = Self {
+            config_name: self.config_name.clone(),
+            format: self.format,
+            ..loaded
         };
-
         Ok(())
     }
 
-    fn save_parameters(&self) -> Result<(), String> {
-        let mut config_instance = Ini::new();
-
-        config_instance.set("serial_settings", "PORT_NAME", Some(self.get_port_name()));
-        config_instance.set(
-            "serial_settings",
-            "BAUD_RATE",
-            Some(self.get_baud_rate().to_string()),
-        );
-        return config_instance
-            .write(format!("configs/serial/{}.ini", self.config_name))
-            .map_err(|e| e.to_string());
-    }
-
-    fn list_existing_configs() -> Result<Vec<String>, String> {
-        let mut list_of_files = Vec::new();
-
-        if let Ok(entries) = fs::read_dir("configs/serial/") {
-            for entry in entries {
-                if let Ok(dir) = entry {
-                    list_of_files.push(
-                        dir.path()
-                            .display()
-                            .to_string()
-                            .replace("configs/serial/", "")
-                            .replace(".ini", ""),
-                    );
-                } else {
-                    break;
-                }
-            }
-            return Ok(list_of_files);
-        }
+    fn save_parameters(&self) -> Result<(), ConfigError> {
+        let path = config_path(CONFIG_DIR, &self.config_name, self.format);
+        write_config(self, &path, self.format, &SCHEMA)
+    }
 
-        return Err("Unable to get config file names".to_string());
+    fn list_existing_configs() -> Result<Vec<String>, ConfigError> {
+        list_configs(CONFIG_DIR)
     }
 }
 
@@ -117,8 +283,189 @@ impl Display for PortConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "\n Config_name: {}.ini \n Port name: {}, \n Baud rate: {}",
-            self.config_name, self.port_name, self.baud_rate
+            "\n Config_name: {}.{} \n Port name: {}, \n Baud rate: {}, \n Response timeout: {} ms, \n Inter-byte timeout: {} ms, \n Checksum: {}, \n Handshake: \"{}\" -> \"{}\" ({}), \n Request retry: {}, \n Heartbeat: {}",
+            self.config_name,
+            self.format,
+            self.serial_settings.port_name,
+            self.serial_settings.baud_rate,
+            self.serial_settings.response_timeout.as_millis(),
+            self.serial_settings.inter_byte_timeout.as_millis(),
+            self.serial_settings.checksum,
+            self.handshake.request,
+            self.handshake.reply,
+            describe_retry(&self.handshake.retry),
+            describe_retry(&self.retry),
+            describe_heartbeat(self.heartbeat.as_ref())
         )
     }
 }
+
+/// Описание политики повторов для вывода настроек
+fn describe_retry(policy: &RetryPolicy) -> String {
+    let mut text = format!(
+        "{} attempt(s), backoff {} ms x{}",
+        policy.attempts,
+        policy.backoff.as_millis(),
+        policy.backoff_factor
+    );
+    if let Some(total) = policy.total_timeout {
+        text.push_str(&format!(", total {} ms", total.as_millis()));
+    }
+    text
+}
+
+/// Описание контроля связи для вывода настроек
+fn describe_heartbeat(policy: Option<&HeartbeatPolicy>) -> String {
+    let Some(policy) = policy else {
+        return HEARTBEAT_OFF.to_string();
+    };
+
+    let mut text = format!(
+        "{} every {} ms, {} missed",
+        policy.mode,
+        policy.interval.as_millis(),
+        policy.missed_limit
+    );
+    if policy.reconnect {
+        text.push_str(", reconnect");
+    }
+    text
+}
+
+/// Контрольная сумма в файле конфига: `crc8`, `crc16` или `xor`
+mod checksum_name {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(checksum: &Checksum, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(checksum)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Checksum, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse()
+            .map_err(|_| de::Error::custom(format!("unknown checksum {:?}", name)))
+    }
+}
+
+/// Необязательная контрольная сумма в файле конфига (пустая строка - не задана)
+mod optional_checksum_name {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        checksum: &Option<Checksum>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match checksum {
+            Some(checksum) => serializer.collect_str(checksum),
+            None => serializer.serialize_str(""),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Checksum>, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        match name.trim() {
+            "" => Ok(None),
+            name => name
+                .parse()
+                .map(Some)
+                .map_err(|_| de::Error::custom(format!("unknown checksum {:?}", name))),
+        }
+    }
+}
+
+/// Политика повторов в файле конфига: ключи `attempts`, `backoff_ms`,
+/// `backoff_factor` и `total_timeout_ms` (0 - без ограничения)
+mod retry_policy {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct RetryKeys {
+        attempts: u8,
+        #[serde(with = "millis")]
+        backoff_ms: Duration,
+        backoff_factor: u32,
+        #[serde(with = "optional_millis")]
+        total_timeout_ms: Option<Duration>,
+    }
+
+    pub fn serialize<S: Serializer>(
+        policy: &RetryPolicy,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        RetryKeys {
+            attempts: policy.attempts,
+            backoff_ms: policy.backoff,
+            backoff_factor: policy.backoff_factor,
+            total_timeout_ms: policy.total_timeout,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<RetryPolicy, D::Error> {
+        let keys = RetryKeys::deserialize(deserializer)?;
+        Ok(RetryPolicy {
+            attempts: keys.attempts,
+            backoff: keys.backoff_ms,
+            backoff_factor: keys.backoff_factor,
+            total_timeout: keys.total_timeout_ms,
+        })
+    }
+}
+
+/// Контроль связи в файле конфига: секция `[heartbeat]` с ключами `mode`
+/// (`off`, `ping`, `traffic`), `interval_ms`, `missed_limit` и `reconnect` (0/1)
+///
+/// При `mode=off` остальные ключи записываются со значениями по умолчанию
+mod heartbeat_policy {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct HeartbeatKeys {
+        mode: String,
+        #[serde(with = "millis")]
+        interval_ms: Duration,
+        missed_limit: u8,
+        reconnect: bool,
+    }
+
+    pub fn serialize<S: Serializer>(
+        policy: &Option<HeartbeatPolicy>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let (mode, policy) = match policy {
+            Some(policy) => (policy.mode.to_string(), policy.clone()),
+            None => (HEARTBEAT_OFF.to_string(), HeartbeatPolicy::default()),
+        };
+        HeartbeatKeys {
+            mode,
+            interval_ms: policy.interval,
+            missed_limit: policy.missed_limit,
+            reconnect: policy.reconnect,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<HeartbeatPolicy>, D::Error> {
+        let keys = HeartbeatKeys::deserialize(deserializer)?;
+        if keys.mode.trim().is_empty() || keys.mode.trim().eq_ignore_ascii_case(HEARTBEAT_OFF) {
+            return Ok(None);
+        }
+
+        let mode: HeartbeatMode = keys
+            .mode
+            .parse()
+            .map_err(|_| de::Error::custom(format!("unknown heartbeat mode {:?}", keys.mode)))?;
+        Ok(Some(HeartbeatPolicy {
+            mode,
+            interval: keys.interval_ms,
+            missed_limit: keys.missed_limit,
+            reconnect: keys.reconnect,
+        }))
+    }
+}
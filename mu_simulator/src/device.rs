@@ -0,0 +1,385 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use misc::config::ConfigIO;
+use misc::device_config::{DeviceConfig, Periodicity};
+use misc::parameter::{PARAMETERS, PERIODICITY_STEP_MS};
+use protocol::checksum::Checksum;
+use protocol::command::{Command, CommandParseError, Response};
+use protocol::device_info::{
+    ACK_CAPABILITY, COMMIT_CAPABILITY, DeviceInfo, PROTOCOL_VERSION, SEQUENCE_CAPABILITY,
+};
+use protocol::event::DeviceEvent;
+
+use crate::elevator::Elevator;
+
+/// Ответ на приветствие
+const GREETING_REPLY: &str = "Hi!";
+
+/// Коды ошибок, возвращаемые устройством в ответе `ERR <code>`
+pub const ERR_UNKNOWN_COMMAND: u8 = 1;
+pub const ERR_UNKNOWN_PARAMETER: u8 = 2;
+pub const ERR_OUT_OF_RANGE: u8 = 3;
+pub const ERR_BAD_VALUE: u8 = 4;
+
+/// Максимальный номер режима стриминга (OnDemandMode)
+const MAX_STREAMING_MODE: u8 = 3;
+
+/// Количество последних запросов в журнале
+const LOG_SIZE: usize = 64;
+
+/// Режимы стриминга, в которых устройство шлет данные само
+pub const ON_CHANGE_MODE: u8 = 1;
+pub const PERIODIC_MODE: u8 = 2;
+
+/// Ответ эмулятора на консольную команду
+#[derive(Debug, PartialEq)]
+pub struct Reply {
+    pub text: String,
+    /// Команда изменила состояние устройства
+    pub state_changed: bool,
+}
+
+/// Модель MCU индикатора
+pub struct SimulatedDevice {
+    config: DeviceConfig,
+    streaming_mode: u8,
+    /// Параметры, запись которых подтверждается, но не выполняется
+    ignored_writes: Vec<String>,
+    /// Последнее зафиксированное командой `commit` состояние
+    committed: DeviceConfig,
+    elevator: Elevator,
+    /// Журнал последних запросов (команда `log`)
+    journal: VecDeque<String>,
+    requests: u32,
+    /// Алгоритм контрольной суммы пакетов
+    checksum: Checksum,
+}
+
+impl SimulatedDevice {
+    pub fn new(config: DeviceConfig, elevator: Elevator) -> Self {
+        Self {
+            committed: config.clone(),
+            config,
+            streaming_mode: 0,
+            ignored_writes: Vec::new(),
+            elevator,
+            journal: VecDeque::with_capacity(LOG_SIZE),
+            requests: 0,
+            checksum: Checksum::default(),
+        }
+    }
+
+    /// Алгоритм контрольной суммы пакетов
+    ///
+    /// После команды `set checksum` меняется до отправки подтверждения:
+    /// подтверждение передается с алгоритмом, действовавшим до команды
+    pub fn get_checksum(&self) -> Checksum {
+        self.checksum
+    }
+
+    pub fn set_checksum(&mut self, checksum: Checksum) {
+        self.checksum = checksum;
+    }
+
+    /// Имитация прошивки, молча игнорирующей запись параметра
+    pub fn ignore_writes(&mut self, parameter: &str) {
+        self.ignored_writes.push(parameter.to_string());
+    }
+
+    pub fn get_config(&self) -> &DeviceConfig {
+        &self.config
+    }
+
+    /// Состояние, зафиксированное последней командой `commit`
+    pub fn get_committed(&self) -> &DeviceConfig {
+        &self.committed
+    }
+
+    pub fn get_streaming_mode(&self) -> u8 {
+        self.streaming_mode
+    }
+
+    /// Период отправки данных в режиме PeriodicMode (`None` - не задан)
+    pub fn stream_period(&self) -> Option<Duration> {
+        match self.config.get::<Periodicity>().0 {
+            0 => None,
+            value => Some(Duration::from_millis(
+                u64::from(value) * PERIODICITY_STEP_MS,
+            )),
+        }
+    }
+
+    /// Шаг модели лифта, возвращает `true`, если состояние изменилось
+    pub fn step(&mut self) -> bool {
+        self.elevator.step()
+    }
+
+    /// Текущее состояние лифта
+    pub fn status(&self) -> &DeviceEvent {
+        self.elevator.status()
+    }
+
+    /// Сведения об эмулируемом устройстве
+    pub fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            model: "MU-SIM".to_string(),
+            firmware_version: env!("CARGO_PKG_VERSION")
+                .parse()
+                .unwrap_or(PROTOCOL_VERSION),
+            protocol_version: PROTOCOL_VERSION,
+            serial_number: Some(format!("SIM-{}", self.config.get_config_name())),
+            parameters: PARAMETERS.iter().map(|spec| spec.key.to_string()).collect(),
+            capabilities: vec![
+                ACK_CAPABILITY.to_string(),
+                COMMIT_CAPABILITY.to_string(),
+                SEQUENCE_CAPABILITY.to_string(),
+            ],
+            checksums: Checksum::ALL.to_vec(),
+        }
+    }
+
+    /// Обработка консольной команды
+    pub fn handle_request(&mut self, request: &str) -> Reply {
+        self.record(request);
+
+        let command = match request.parse::<Command>() {
+            Ok(command) => command,
+            Err(CommandParseError::UnknownCommand) => return Self::error(ERR_UNKNOWN_COMMAND),
+            Err(CommandParseError::BadValue) => return Self::error(ERR_BAD_VALUE),
+        };
+
+        match command {
+            Command::Hello => Self::reply(Response::Greeting(GREETING_REPLY.to_string()), false),
+            Command::Info => Self::reply(Response::Info(self.device_info()), false),
+            Command::Get(parameter) => match self.get_parameter(&parameter) {
+                Some(value) => Self::reply(
+                    Response::Value {
+                        parameter,
+                        value: u32::from(value),
+                    },
+                    false,
+                ),
+                None => Self::error(ERR_UNKNOWN_PARAMETER),
+            },
+            Command::SetMode(mode) if mode <= MAX_STREAMING_MODE => {
+                self.streaming_mode = mode;
+                Self::reply(Response::Ack, false)
+            }
+            Command::SetMode(_) => Self::error(ERR_OUT_OF_RANGE),
+            Command::SetChecksum(checksum) => {
+                self.checksum = checksum;
+                Self::reply(Response::Ack, false)
+            }
+            Command::Set(parameter, value) => match self.set_parameter(&parameter, value) {
+                Ok(()) => Self::reply(Response::Ack, true),
+                Err(code) => Self::error(code),
+            },
+            Command::Reset => {
+                self.config = DeviceConfig::new(&self.config.get_config_name());
+                Self::reply(Response::Ack, true)
+            }
+            Command::Commit => {
+                self.committed = self.config.clone();
+                Self::reply(Response::Ack, false)
+            }
+            Command::Log => {
+                Self::reply(Response::Log(self.journal.iter().cloned().collect()), false)
+            }
+        }
+    }
+
+    /// Запись запроса в журнал
+    fn record(&mut self, request: &str) {
+        self.requests += 1;
+        if self.journal.len() == LOG_SIZE {
+            self.journal.pop_front();
+        }
+        self.journal
+            .push_back(format!("#{:04} {}", self.requests, request));
+    }
+
+    /// Полезная нагрузка пакета потоковых данных
+    pub fn stream_payload(&self) -> Vec<u8> {
+        self.elevator.status().to_payload()
+    }
+
+    fn get_parameter(&self, parameter: &str) -> Option<u8> {
+        self.config.get_value(parameter)
+    }
+
+    fn set_parameter(&mut self, parameter: &str, value: u32) -> Result<(), u8> {
+        if self.get_parameter(parameter).is_none() {
+            return Err(ERR_UNKNOWN_PARAMETER);
+        }
+        let value = u8::try_from(value).map_err(|_| ERR_OUT_OF_RANGE)?;
+
+        if self.ignored_writes.iter().any(|p| p == parameter) {
+            return Ok(());
+        }
+
+        self.config
+            .set_value(parameter, value)
+            .map_err(|_| ERR_OUT_OF_RANGE)
+    }
+
+    fn reply(response: Response, state_changed: bool) -> Reply {
+        Reply {
+            text: response.to_string(),
+            state_changed,
+        }
+    }
+
+    fn error(code: u8) -> Reply {
+        Self::reply(Response::Error(code), false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use misc::device_config::LoadCapacityIdx;
+
+    fn device() -> SimulatedDevice {
+        SimulatedDevice::new(DeviceConfig::new("simulator"), Elevator::new(1, 9))
+    }
+
+    #[test]
+    fn test_handshake() {
+        assert_eq!(device().handle_request("hello").text, "Hi!\r\n");
+    }
+
+    #[test]
+    fn test_server_info() {
+        let reply = device().handle_request("get server_info");
+        let info = reply.text.parse::<DeviceInfo>().unwrap();
+
+        assert_eq!(info, device().device_info());
+        assert!(info.supports_parameter("loadcapacity"));
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let mut device = device();
+
+        let reply = device.handle_request("set groupnumber 7");
+        assert_eq!(reply.text, "OK\r\n");
+        assert!(reply.state_changed);
+
+        assert_eq!(
+            device.handle_request("get groupnumber").text,
+            "groupnumber:7\r\n"
+        );
+        assert_eq!(device.handle_request("set musicvolume 9").text, "ERR 3\r\n");
+        assert_eq!(
+            device.handle_request("get musicvolume").text,
+            "musicvolume:0\r\n"
+        );
+    }
+
+    #[test]
+    fn test_ignored_writes() {
+        let mut device = device();
+        device.ignore_writes("groupnumber");
+
+        assert_eq!(device.handle_request("set groupnumber 5").text, "OK\r\n");
+        assert_eq!(
+            device.handle_request("get groupnumber").text,
+            "groupnumber:0\r\n"
+        );
+    }
+
+    #[test]
+    fn test_commit() {
+        let mut device = device();
+
+        device.handle_request("set loadcapacity 5");
+        assert_eq!(device.get_committed().get::<LoadCapacityIdx>().0, 0);
+        assert_eq!(device.handle_request("commit").text, "OK\r\n");
+        assert_eq!(device.get_committed().get::<LoadCapacityIdx>().0, 5);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut device = device();
+
+        device.handle_request("set soundvolume 4");
+        let reply = device.handle_request("reset");
+        assert_eq!(reply.text, "OK\r\n");
+        assert!(reply.state_changed);
+        assert_eq!(
+            device.handle_request("get soundvolume").text,
+            "soundvolume:2\r\n"
+        );
+    }
+
+    #[test]
+    fn test_streaming_mode() {
+        let mut device = device();
+
+        assert_eq!(device.handle_request("set mode 2").text, "OK\r\n");
+        assert_eq!(device.get_streaming_mode(), PERIODIC_MODE);
+        assert_eq!(device.handle_request("set mode 4").text, "ERR 3\r\n");
+        assert_eq!(device.handle_request("set mode x").text, "ERR 4\r\n");
+    }
+
+    #[test]
+    fn test_periodicity() {
+        let mut device = device();
+
+        assert_eq!(device.stream_period(), Some(Duration::from_secs(1)));
+        assert_eq!(device.handle_request("set periodicity 5").text, "OK\r\n");
+        assert_eq!(device.stream_period(), Some(Duration::from_millis(500)));
+        assert_eq!(device.handle_request("set periodicity 0").text, "OK\r\n");
+        assert_eq!(device.stream_period(), None);
+        assert_eq!(
+            device.handle_request("set periodicity 101").text,
+            "ERR 3\r\n"
+        );
+    }
+
+    #[test]
+    fn test_stream_payload() {
+        let mut device = device();
+
+        assert!(device.step());
+        let event = DeviceEvent::from_payload(&device.stream_payload()).unwrap();
+        assert_eq!(&event, device.status());
+    }
+
+    #[test]
+    fn test_log() {
+        let mut device = device();
+
+        for i in 0..LOG_SIZE {
+            device.handle_request(&format!("set groupnumber {}", i % 16));
+        }
+        let reply = device.handle_request("log");
+
+        let lines = reply.text.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), LOG_SIZE);
+        assert_eq!(lines[0], "#0002 set groupnumber 1");
+        assert_eq!(lines[LOG_SIZE - 1], "#0065 log");
+        assert!(reply.text.len() > 255);
+    }
+
+    #[test]
+    fn test_set_checksum() {
+        let mut device = device();
+        assert!(device.device_info().supports_checksum(Checksum::Xor));
+
+        assert_eq!(device.handle_request("set checksum xor").text, "OK\r\n");
+        assert_eq!(device.get_checksum(), Checksum::Xor);
+        assert_eq!(device.handle_request("set checksum md5").text, "ERR 4\r\n");
+        assert_eq!(device.get_checksum(), Checksum::Xor);
+    }
+
+    #[test]
+    fn test_unknown_requests() {
+        let mut device = device();
+
+        assert_eq!(device.handle_request("reboot").text, "ERR 1\r\n");
+        assert_eq!(device.handle_request("set colour 1").text, "ERR 2\r\n");
+        assert_eq!(device.handle_request("get colour").text, "ERR 2\r\n");
+    }
+}
@@ -0,0 +1,135 @@
+use protocol::event::{DeviceEvent, Direction, DoorState};
+
+/// Модель движения кабины, формирующая потоковые данные эмулятора
+///
+/// Кабина обслуживает вызовы на случайные этажи: разгон, движение по этажам,
+/// остановка с гонгом и цикл открытия/закрытия дверей
+pub struct Elevator {
+    status: DeviceEvent,
+    lowest: i8,
+    highest: i8,
+    target: i8,
+    /// Состояние генератора вызовов (повторяемая последовательность)
+    seed: u32,
+}
+
+impl Elevator {
+    pub fn new(lowest: i8, highest: i8) -> Self {
+        let highest = highest.max(lowest);
+
+        Self {
+            status: DeviceEvent {
+                floor: lowest,
+                ..Default::default()
+            },
+            lowest,
+            highest,
+            target: lowest,
+            seed: 1,
+        }
+    }
+
+    pub fn status(&self) -> &DeviceEvent {
+        &self.status
+    }
+
+    /// Установка активных кодов неисправностей
+    pub fn set_faults(&mut self, faults: Vec<u16>) {
+        self.status.out_of_service = !faults.is_empty();
+        self.status.faults = faults;
+    }
+
+    /// Шаг модели, возвращает `true`, если состояние изменилось
+    pub fn step(&mut self) -> bool {
+        let previous = self.status.clone();
+        // Гонг звучит только в момент прибытия
+        self.status.arrival_gong = false;
+
+        match self.status.door {
+            DoorState::Opening => self.status.door = DoorState::Open,
+            DoorState::Open => self.status.door = DoorState::Closing,
+            DoorState::Closing => self.status.door = DoorState::Closed,
+            DoorState::Closed if self.status.out_of_service => {
+                self.status.direction = Direction::Idle
+            }
+            DoorState::Closed if self.status.floor == self.target => {
+                if self.status.direction == Direction::Idle {
+                    self.target = self.next_call();
+                    self.status.direction = if self.target > self.status.floor {
+                        Direction::Up
+                    } else {
+                        Direction::Down
+                    };
+                } else {
+                    self.status.direction = Direction::Idle;
+                    self.status.arrival_gong = true;
+                    self.status.door = DoorState::Opening;
+                }
+            }
+            DoorState::Closed => match self.status.direction {
+                Direction::Up => self.status.floor += 1,
+                Direction::Down => self.status.floor -= 1,
+                Direction::Idle => (),
+            },
+        }
+
+        self.status != previous
+    }
+
+    /// Следующий вызов на этаж, отличный от текущего
+    fn next_call(&mut self) -> i8 {
+        if self.lowest == self.highest {
+            return self.lowest;
+        }
+
+        self.seed = self.seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        let span = (i16::from(self.highest) - i16::from(self.lowest)) as u32;
+        let offset = (self.seed >> 16) % span;
+
+        // Текущий этаж исключается сдвигом на следующий
+        let floor = i16::from(self.lowest) + offset as i16;
+        if floor >= i16::from(self.status.floor) {
+            (floor + 1) as i8
+        } else {
+            floor as i8
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trip() {
+        let mut elevator = Elevator::new(1, 9);
+        let mut arrivals = 0;
+
+        for _ in 0..200 {
+            assert!(elevator.step());
+
+            let status = elevator.status();
+            assert!((1..=9).contains(&status.floor));
+            if status.arrival_gong {
+                arrivals += 1;
+                assert_eq!(status.door, DoorState::Opening);
+                assert_eq!(status.direction, Direction::Idle);
+            }
+            if status.door != DoorState::Closed {
+                assert_eq!(status.direction, Direction::Idle);
+            }
+        }
+
+        assert!(arrivals > 5);
+    }
+
+    #[test]
+    fn test_out_of_service() {
+        let mut elevator = Elevator::new(-1, 5);
+        elevator.set_faults(vec![0x0012]);
+
+        assert!(!elevator.step());
+        assert!(elevator.status().out_of_service);
+        assert_eq!(elevator.status().floor, -1);
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+/// Искажение очередного ответа эмулятора
+#[derive(Debug, PartialEq)]
+pub enum ReplyFault {
+    /// Ответ отправляется без изменений
+    None,
+    /// Ответ не отправляется
+    Drop,
+    /// Ответ отправляется с неверным CRC
+    Corrupt,
+}
+
+/// Сценарий некорректного поведения эмулятора
+#[derive(Debug, Default)]
+pub struct FaultScript {
+    /// Задержка перед отправкой ответа
+    pub reply_delay: Duration,
+    /// Портить CRC каждого N-го ответа (0 - отключено)
+    pub corrupt_every: u32,
+    /// Пропускать каждый N-й ответ (0 - отключено)
+    pub drop_every: u32,
+    replies: u32,
+}
+
+impl FaultScript {
+    pub fn new(reply_delay: Duration, corrupt_every: u32, drop_every: u32) -> Self {
+        Self {
+            reply_delay,
+            corrupt_every,
+            drop_every,
+            replies: 0,
+        }
+    }
+
+    /// Определение искажения для очередного ответа
+    pub fn next_reply(&mut self) -> ReplyFault {
+        self.replies = self.replies.wrapping_add(1);
+
+        if self.drop_every != 0 && self.replies.is_multiple_of(self.drop_every) {
+            return ReplyFault::Drop;
+        }
+
+        if self.corrupt_every != 0 && self.replies.is_multiple_of(self.corrupt_every) {
+            return ReplyFault::Corrupt;
+        }
+
+        ReplyFault::None
+    }
+}
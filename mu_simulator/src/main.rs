@@ -0,0 +1,320 @@
+// Эмулятор MCU индикатора для разработки без оборудования
+//
+// $ RUST_LOG=info ./mu_simulator                       # PTY, имя порта выводится в stdout
+// $ RUST_LOG=info ./mu_simulator --tcp 127.0.0.1:5000  # TCP, адрес клиента tcp://127.0.0.1:5000
+mod device;
+mod elevator;
+mod faults;
+
+use std::io;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use device::{ON_CHANGE_MODE, PERIODIC_MODE, SimulatedDevice};
+use elevator::Elevator;
+use faults::{FaultScript, ReplyFault};
+use log::{debug, info, warn};
+use misc::config::{ConfigIO, set_config_root};
+use misc::device_config::DeviceConfig;
+use protocol::checksum::Checksum;
+use protocol::decoder::FrameDecoder;
+use protocol::event::STATUS_REQUEST;
+use protocol::fragment::{Message, Reassembler};
+use protocol::opcode::Opcode;
+use protocol::transport::{SerialTransport, TcpTransport, Transport};
+
+/// Период опроса канала
+const POLL_INTERVAL_MS: u64 = 20;
+
+#[derive(Parser)]
+#[command(author = "MU LLC", name = "mu_simulator", version = "0.1.0", about, long_about = None)]
+struct Args {
+    /// Адрес для приема TCP подключений (например, 127.0.0.1:5000). Без него создается PTY
+    #[arg(short = 't', long = "tcp")]
+    tcp_address: Option<String>,
+    /// Имя конфиг файла с начальным состоянием устройства
+    #[arg(short = 'c', long = "config")]
+    config_name: Option<String>,
+    /// Корневой каталог конфигов (подкаталоги device/ и serial/)
+    #[arg(long = "config-root")]
+    config_root: Option<PathBuf>,
+    /// Задержка ответа, мс
+    #[arg(long = "delay-ms", default_value_t = 0)]
+    delay_ms: u64,
+    /// Портить CRC каждого N-го ответа (0 - отключено)
+    #[arg(long = "corrupt-every", default_value_t = 0)]
+    corrupt_every: u32,
+    /// Не отвечать на каждый N-й запрос (0 - отключено)
+    #[arg(long = "drop-every", default_value_t = 0)]
+    drop_every: u32,
+    /// Период отправки потоковых данных, если не задан параметр periodicity, мс
+    #[arg(long = "stream-period-ms", default_value_t = 1000)]
+    stream_period_ms: u64,
+    /// Период шага модели лифта, мс
+    #[arg(long = "step-ms", default_value_t = 1000)]
+    step_ms: u64,
+    /// Нижний этаж модели лифта
+    #[arg(
+        long = "lowest-floor",
+        default_value_t = 1,
+        allow_negative_numbers = true
+    )]
+    lowest_floor: i8,
+    /// Верхний этаж модели лифта
+    #[arg(
+        long = "highest-floor",
+        default_value_t = 9,
+        allow_negative_numbers = true
+    )]
+    highest_floor: i8,
+    /// Код активной неисправности (можно указать несколько раз)
+    #[arg(long = "fault")]
+    faults: Vec<u16>,
+    /// Отправлять потоковые данные независимо от режима стриминга
+    #[arg(long = "unsolicited")]
+    unsolicited: bool,
+    /// Подтверждать запись параметра, не изменяя его (можно указать несколько раз)
+    #[arg(long = "ignore-writes")]
+    ignore_writes: Vec<String>,
+    /// Контрольная сумма пакетов при подключении: crc8, crc16 или xor
+    #[arg(long = "checksum", default_value = "crc8", value_parser = parse_checksum)]
+    checksum: Checksum,
+}
+
+fn parse_checksum(value: &str) -> Result<Checksum, String> {
+    value
+        .parse()
+        .map_err(|_| format!("unknown checksum: {}", value))
+}
+
+/// Параметры потоковых данных
+struct StreamSettings {
+    period: Duration,
+    unsolicited: bool,
+    /// Период шага модели лифта
+    step: Duration,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    env_logger::init();
+
+    if let Some(root) = &args.config_root {
+        set_config_root(root);
+    }
+    let config = match &args.config_name {
+        Some(name) => DeviceConfig::create_from_existing(name)?,
+        None => DeviceConfig::new("simulator"),
+    };
+    info!("Initial device state: {}", config);
+
+    let mut elevator = Elevator::new(args.lowest_floor, args.highest_floor);
+    elevator.set_faults(args.faults.clone());
+
+    let mut device = SimulatedDevice::new(config, elevator);
+    for parameter in &args.ignore_writes {
+        device.ignore_writes(parameter);
+    }
+    let mut faults = FaultScript::new(
+        Duration::from_millis(args.delay_ms),
+        args.corrupt_every,
+        args.drop_every,
+    );
+    let stream = StreamSettings {
+        period: Duration::from_millis(args.stream_period_ms),
+        unsolicited: args.unsolicited,
+        step: Duration::from_millis(args.step_ms),
+    };
+
+    match &args.tcp_address {
+        Some(address) => {
+            let listener = TcpListener::bind(address)?;
+            println!("tcp://{}", listener.local_addr()?);
+
+            for connection in listener.incoming() {
+                let connection = connection?;
+                info!("Client connected: {}", connection.peer_addr()?);
+
+                let mut transport =
+                    TcpTransport::from_stream(connection, Duration::from_millis(POLL_INTERVAL_MS))?;
+                device.set_checksum(args.checksum);
+                if let Err(e) = serve(&mut transport, &mut device, &mut faults, &stream) {
+                    warn!("Session closed: {}", e);
+                }
+                info!(
+                    "Client disconnected, device state: {}, committed: {}",
+                    device.get_config(),
+                    device.get_committed()
+                );
+            }
+        }
+        None => {
+            // Slave сторона удерживается открытой, чтобы чтение master не завершалось ошибкой
+            let (mut master, slave) = SerialTransport::pty_pair()?;
+            println!("{}", slave.name());
+
+            device.set_checksum(args.checksum);
+            serve(&mut master, &mut device, &mut faults, &stream)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Обслуживание одного подключения до его закрытия
+fn serve(
+    transport: &mut dyn Transport,
+    device: &mut SimulatedDevice,
+    faults: &mut FaultScript,
+    stream: &StreamSettings,
+) -> io::Result<()> {
+    transport.set_timeout(Duration::from_millis(POLL_INTERVAL_MS))?;
+
+    let mut decoder = FrameDecoder::with_checksum(device.get_checksum());
+    let mut reassembler = Reassembler::new();
+    let mut read_buffer = [0; 256];
+    let mut last_stream = Instant::now();
+    let mut last_step = Instant::now();
+
+    loop {
+        match transport.read(&mut read_buffer) {
+            Ok(0) => return Ok(()),
+            Ok(size) => match read_buffer.get(..size) {
+                Some(bytes) => decoder.push(bytes),
+                None => return Err(io::Error::from(io::ErrorKind::InvalidData)),
+            },
+            // Данных нет: переход к отправке потоковых данных
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => (),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(e) => return Err(e),
+        }
+
+        while let Some(frame) = decoder.next_frame() {
+            let message = match reassembler.push(frame) {
+                Ok(Some(message)) => message,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Message dropped: {}", e);
+                    continue;
+                }
+            };
+
+            match message.get_opcode() {
+                Opcode::Console => (),
+                // Запрос состояния в режиме OnDemand
+                Opcode::Streaming if message.get_data()[..] == [STATUS_REQUEST] => {
+                    send_stream(transport, device, message.get_id())?;
+                    continue;
+                }
+                opcode => {
+                    warn!("Unsupported frame: {}", opcode);
+                    continue;
+                }
+            }
+
+            let request = String::from_utf8_lossy(message.get_data())
+                .trim()
+                .to_string();
+            // Подтверждение смены контрольной суммы передается по-старому
+            let checksum = device.get_checksum();
+            let reply = device.handle_request(&request);
+            debug!("Request: {:?}, reply: {:?}", request, reply.text);
+
+            send_reply(transport, faults, &reply.text, checksum, message.get_id())?;
+            if device.get_checksum() != checksum {
+                info!("Checksum switched to {}", device.get_checksum());
+                decoder.set_checksum(device.get_checksum());
+            }
+
+            if reply.state_changed && device.get_streaming_mode() == ON_CHANGE_MODE {
+                send_stream(transport, device, None)?;
+            }
+        }
+
+        if last_step.elapsed() >= stream.step {
+            last_step = Instant::now();
+            if device.step() && device.get_streaming_mode() == ON_CHANGE_MODE {
+                send_stream(transport, device, None)?;
+            }
+        }
+
+        let (periodic, period) = if device.get_streaming_mode() == PERIODIC_MODE {
+            (true, device.stream_period().unwrap_or(stream.period))
+        } else {
+            (stream.unsolicited, stream.period)
+        };
+        if periodic && last_stream.elapsed() >= period {
+            send_stream(transport, device, None)?;
+            last_stream = Instant::now();
+        }
+    }
+}
+
+/// Отправка ответа с учетом сценария неисправностей
+///
+/// Длинный ответ передается фрагментами, неисправность применяется
+/// к ответу целиком (пропуск) или к последнему пакету (CRC).
+/// Ответ на нумерованный запрос повторяет его номер
+fn send_reply(
+    transport: &mut dyn Transport,
+    faults: &mut FaultScript,
+    text: &str,
+    checksum: Checksum,
+    id: Option<u8>,
+) -> io::Result<()> {
+    let frames = Message::new(Opcode::Console, text.as_bytes().to_vec())
+        .map(|message| with_id(message, id))
+        .and_then(|message| message.to_frames(checksum))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut bytes = frames
+        .iter()
+        .flat_map(|frame| frame.serialize())
+        .collect::<Vec<u8>>();
+
+    thread::sleep(faults.reply_delay);
+
+    match faults.next_reply() {
+        ReplyFault::None => (),
+        ReplyFault::Drop => {
+            warn!("Dropping reply: {:?}", text);
+            return Ok(());
+        }
+        ReplyFault::Corrupt => {
+            warn!("Corrupting reply CRC: {:?}", text);
+            let crc_index = bytes.len() - 2;
+            bytes[crc_index] ^= 0xFF;
+        }
+    }
+
+    transport.write_all(&bytes)?;
+    transport.flush()
+}
+
+/// Отправка потоковых данных (`id` - номер запроса состояния)
+fn send_stream(
+    transport: &mut dyn Transport,
+    device: &SimulatedDevice,
+    id: Option<u8>,
+) -> io::Result<()> {
+    let frames = Message::new(Opcode::Streaming, device.stream_payload())
+        .map(|message| with_id(message, id))
+        .and_then(|message| message.to_frames(device.get_checksum()))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    debug!("Streaming: {}", device.status());
+    for frame in frames {
+        transport.write_all(&frame.serialize())?;
+    }
+    transport.flush()
+}
+
+fn with_id(message: Message, id: Option<u8>) -> Message {
+    match id {
+        Some(id) => message.with_id(id),
+        None => message,
+    }
+}
@@ -0,0 +1,49 @@
+// Прием "шума на линии": декодер, сборка сообщений и разбор полезной нагрузки
+//
+// $ cargo +nightly fuzz run decoder
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::checksum::Checksum;
+use protocol::command::Command;
+use protocol::decoder::FrameDecoder;
+use protocol::device_info::DeviceInfo;
+use protocol::event::DeviceEvent;
+use protocol::fragment::Reassembler;
+use protocol::opcode::Opcode;
+
+fuzz_target!(|data: &[u8]| {
+    // Первый байт задает алгоритм контрольной суммы (старшие биты)
+    // и размер порции, как при чтении из порта (младшие биты)
+    let Some((&control, data)) = data.split_first() else {
+        return;
+    };
+    let checksum = Checksum::ALL[usize::from(control >> 6) % Checksum::ALL.len()];
+    let chunk = usize::from(control & 0x3F).max(1);
+
+    let mut decoder = FrameDecoder::with_checksum(checksum);
+    let mut reassembler = Reassembler::new();
+
+    for part in data.chunks(chunk) {
+        decoder.push(part);
+
+        while let Some(frame) = decoder.next_frame() {
+            let Ok(Some(message)) = reassembler.push(frame) else {
+                continue;
+            };
+
+            match message.get_opcode() {
+                Opcode::Streaming => {
+                    let _ = DeviceEvent::from_payload(message.get_data());
+                }
+                Opcode::Console => {
+                    let text = String::from_utf8_lossy(message.get_data());
+                    let _ = text.parse::<Command>();
+                    let _ = text.parse::<DeviceInfo>();
+                    let _ = Command::Log.parse_response(&text);
+                }
+                _ => (),
+            }
+        }
+    }
+});
@@ -0,0 +1,17 @@
+// Разбор одного пакета из произвольных байтов
+//
+// $ cargo +nightly fuzz run deserialize
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::checksum::Checksum;
+use protocol::mu_frame::MUFrame;
+
+fuzz_target!(|data: &[u8]| {
+    for checksum in Checksum::ALL {
+        if let Ok(frame) = MUFrame::deserialize_with(data, checksum) {
+            // Принятый пакет сериализуется обратно в те же байты
+            assert_eq!(frame.serialize(), data);
+        }
+    }
+});
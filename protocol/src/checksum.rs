@@ -0,0 +1,114 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::FrameError;
+use crate::payload::PayloadReader;
+
+/// Алгоритм контрольной суммы пакета
+///
+/// Контрольная сумма вычисляется по опкоду и данным пакета и передается
+/// перед постфиксом (многобайтовые суммы - в порядке little-endian).
+/// Алгоритм выбирается для соединения: задается в настройках порта
+/// или согласуется при подключении
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Checksum {
+    /// CRC-8/NRSC-5 (исходные платы "МЮ")
+    #[default]
+    Crc8,
+    /// CRC-16/MODBUS
+    Crc16,
+    /// Побайтовое исключающее ИЛИ
+    Xor,
+}
+
+impl Checksum {
+    /// Все поддерживаемые алгоритмы
+    pub const ALL: [Checksum; 3] = [Checksum::Crc8, Checksum::Crc16, Checksum::Xor];
+
+    /// Размер контрольной суммы в пакете, байт
+    pub fn width(&self) -> usize {
+        match self {
+            Checksum::Crc8 | Checksum::Xor => 1,
+            Checksum::Crc16 => 2,
+        }
+    }
+
+    /// Вычисление контрольной суммы
+    pub fn compute(&self, data: &[u8]) -> u16 {
+        const CRC8_MU: crc::Crc<u8> = crc::Crc::<u8>::new(&crc::CRC_8_NRSC_5);
+        const CRC16_MU: crc::Crc<u16> = crc::Crc::<u16>::new(&crc::CRC_16_MODBUS);
+
+        match self {
+            Checksum::Crc8 => u16::from(CRC8_MU.checksum(data)),
+            Checksum::Crc16 => CRC16_MU.checksum(data),
+            Checksum::Xor => u16::from(data.iter().fold(0, |sum, byte| sum ^ byte)),
+        }
+    }
+
+    /// Запись контрольной суммы в пакет
+    pub(crate) fn write(&self, value: u16, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&value.to_le_bytes()[..self.width()]);
+    }
+
+    /// Чтение контрольной суммы из пакета
+    pub(crate) fn read(&self, reader: &mut PayloadReader) -> Result<u16, FrameError> {
+        match self.width() {
+            1 => reader.get_u8().map(u16::from),
+            _ => reader.get_u16(),
+        }
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "crc8" => Ok(Checksum::Crc8),
+            "crc16" => Ok(Checksum::Crc16),
+            "xor" => Ok(Checksum::Xor),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Checksum::Crc8 => write!(f, "crc8"),
+            Checksum::Crc16 => write!(f, "crc16"),
+            Checksum::Xor => write!(f, "xor"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_values() {
+        let data = b"123456789";
+        assert_eq!(Checksum::Crc16.compute(data), 0x4B37);
+        assert_eq!(Checksum::Xor.compute(&[0xC0, 0x0F, 0x01]), 0xCE);
+        assert_eq!(Checksum::Crc8.compute(&[]), 0xFF);
+
+        let mut bytes = Vec::new();
+        Checksum::Crc16.write(0x4B37, &mut bytes);
+        Checksum::Xor.write(0xCE, &mut bytes);
+        assert_eq!(bytes, vec![0x37, 0x4B, 0xCE]);
+
+        let mut reader = PayloadReader::new(&bytes);
+        assert_eq!(Checksum::Crc16.read(&mut reader), Ok(0x4B37));
+        assert_eq!(Checksum::Xor.read(&mut reader), Ok(0xCE));
+    }
+
+    #[test]
+    fn test_checksum_names() {
+        for checksum in Checksum::ALL {
+            assert_eq!(checksum.to_string().parse(), Ok(checksum));
+        }
+        assert_eq!("CRC16".parse(), Ok(Checksum::Crc16));
+        assert_eq!("crc32".parse::<Checksum>(), Err(()));
+    }
+}
@@ -1,69 +1,1801 @@
-use log::warn;
+use log::{debug, info, warn};
 
-use crate::mu_frame::MUFrame;
-use std::time::Duration;
+use crate::checksum::Checksum;
+use crate::command::{Command, Response};
+use crate::connection::{ConnectionMonitor, ConnectionState, Heartbeat};
+use crate::device_info::{ACK_CAPABILITY, DeviceInfo, PROTOCOL_VERSION, SEQUENCE_CAPABILITY};
+use crate::error::ClientError;
+use crate::event::{DeviceEvent, STATUS_REQUEST};
+use crate::fragment::Message;
+use crate::opcode::Opcode;
+use crate::options::{ClientOptions, ResponseTimeouts, RetryPolicy};
+use crate::reader::{BackgroundReader, Shared, lock};
+use crate::transport::{self, Transport};
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
 
+pub use crate::reader::MessageHandler;
+
+/// Максимальное число запросов, отправляемых без ожидания ответов
+const PIPELINE_DEPTH: usize = 8;
+
+/// Открытие канала к устройству (при подключении и переподключении)
+pub type Connector = Box<dyn FnMut() -> Result<Box<dyn Transport>, ClientError> + Send>;
+
+/// Клиент протокола "МЮ" на стороне хоста
+///
+/// Прием ведется фоновым потоком: ответы передаются ожидающему запросу,
+/// остальные сообщения (например, потоковые данные) - обработчикам
+/// (`register_handler`) и подписчикам (`subscribe`).
+///
+/// Если устройство поддерживает нумерацию запросов, каждый запрос
+/// получает номер, а ответы с чужим номером (например, опоздавшие
+/// ответы на повторенные запросы) отбрасываются.
+///
+/// При включенном контроле связи (`ClientOptions::heartbeat`) состояние
+/// соединения отслеживается фоновым потоком, после потери связи клиент
+/// переподключается при следующем запросе
 pub struct HostClient {
-    serial_port: Box<dyn serialport::SerialPort + 'static>,
+    link: Arc<Mutex<Link>>,
+    reader: BackgroundReader,
+    options: ClientOptions,
+    device_info: Option<DeviceInfo>,
+    /// Состояние соединения (общее с потоками чтения и контроля связи)
+    connection: Arc<ConnectionMonitor>,
+    heartbeat: Option<Heartbeat>,
+    /// Открытие канала заново (`None` - переподключение невозможно)
+    connector: Option<Connector>,
+    /// Алгоритм контрольной суммы при подключении (до согласования)
+    initial_checksum: Checksum,
+}
+
+/// Канал обмена запросами: отправка, прием ответов и нумерация запросов
+///
+/// Общий для клиента и потока контроля связи. Запрос выполняет тот,
+/// кто захватил канал, поэтому ответы не достаются чужому запросу
+pub(crate) struct Link {
+    /// Имя канала (для логов)
+    name: String,
+    /// Канал для отправки запросов (`None` - закрыт перед переподключением)
+    transport: Option<Box<dyn Transport>>,
+    replies: Receiver<Result<Message, ClientError>>,
+    /// Номер следующего запроса (`None` - нумерация отключена)
+    next_id: Option<u8>,
+}
+
+impl Link {
+    /// Закрытие канала отправки
+    fn close(&mut self) {
+        self.transport = None;
+    }
+
+    /// Присвоение сообщению номера очередного запроса
+    fn tag(&mut self, message: &Message) -> Message {
+        match self.next_id {
+            Some(id) => {
+                self.next_id = Some(id.wrapping_add(1));
+                message.clone().with_id(id)
+            }
+            None => message.clone(),
+        }
+    }
+
+    /// Отправка сообщения (фрагментами, если оно не помещается в пакет)
+    fn send(&mut self, message: &Message, checksum: Checksum) -> Result<(), ClientError> {
+        let transport = self
+            .transport
+            .as_mut()
+            .ok_or(ClientError::ConnectionClosed)?;
+
+        message
+            .to_frames(checksum)?
+            .into_iter()
+            .try_for_each(|frame| crate::send_proto_message(frame, transport))
+    }
+
+    /// Однократная отправка сообщения и ожидание ответа с тем же опкодом
+    /// (и номером запроса)
+    ///
+    /// Ответ возвращается сразу после сборки, но не позднее
+    /// крайнего срока `response` с момента отправки
+    pub(crate) fn exchange(
+        &mut self,
+        shared: &Shared,
+        message: &Message,
+        response: Duration,
+    ) -> Result<Message, ClientError> {
+        // Ответы, опоздавшие к предыдущему запросу
+        while self.replies.try_recv().is_ok() {}
+
+        let message = self.tag(message);
+        shared
+            .router()
+            .expect_reply(message.get_opcode(), message.get_id())?;
+
+        let result = self.send(&message, shared.get_checksum()).and_then(|()| {
+            match self.replies.recv_timeout(response) {
+                Ok(reply) => reply,
+                Err(RecvTimeoutError::Timeout) => Err(ClientError::Timeout),
+                Err(RecvTimeoutError::Disconnected) => Err(ClientError::ConnectionClosed),
+            }
+        });
+
+        shared.router().clear_expected();
+        result
+    }
+
+    /// Однократная отправка сообщений, еще не получивших ответа, и прием ответов
+    ///
+    /// Ответы записываются в `replies` по индексам сообщений. Крайний срок
+    /// `response` отсчитывается от отправки последнего сообщения
+    fn exchange_pipelined(
+        &mut self,
+        shared: &Shared,
+        messages: &[Message],
+        replies: &mut [Option<Message>],
+        response: Duration,
+    ) -> Result<(), ClientError> {
+        // Ответы, опоздавшие к предыдущему запросу
+        while self.replies.try_recv().is_ok() {}
+
+        let mut pending = HashMap::new();
+        let mut result = Ok(());
+
+        for (index, (message, reply)) in messages.iter().zip(replies.iter()).enumerate() {
+            if reply.is_some() {
+                continue;
+            }
+
+            let message = self.tag(message);
+            result = shared
+                .router()
+                .expect_reply(message.get_opcode(), message.get_id())
+                .and_then(|()| self.send(&message, shared.get_checksum()));
+            if result.is_err() {
+                break;
+            }
+            pending.insert(message.get_id(), index);
+        }
+
+        let deadline = Instant::now() + response;
+        while result.is_ok() && !pending.is_empty() {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            match self.replies.recv_timeout(wait) {
+                Ok(Ok(message)) => {
+                    if let Some(slot) = pending
+                        .remove(&message.get_id())
+                        .and_then(|index| replies.get_mut(index))
+                    {
+                        *slot = Some(message);
+                    }
+                }
+                Ok(Err(e)) => result = Err(e),
+                Err(RecvTimeoutError::Timeout) => result = Err(ClientError::Timeout),
+                Err(RecvTimeoutError::Disconnected) => result = Err(ClientError::ConnectionClosed),
+            }
+        }
+
+        shared.router().clear_expected();
+        result
+    }
+
+    /// Сброс недопринятых данных перед повтором запроса
+    fn discard_input(&mut self) {
+        if let Some(Err(e)) = self.transport.as_mut().map(|t| t.clear_input()) {
+            debug!("Unable to clear input: {}", e);
+        }
+    }
 }
 
 impl HostClient {
-    /// Подключение к устройству
+    /// Подключение к устройству
+    ///
+    /// `port_name` - имя последовательного порта, `tcp://host:port` или `unix:///path`.
+    /// При переподключении порт открывается заново
     pub fn connect(
         port_name: &str,
         baudrate: u32,
-        timeout: Duration,
-    ) -> Result<HostClient, String> {
-        let serial_port = serialport::new(port_name, baudrate)
-            .timeout(timeout)
-            .open()
-            .expect(format!("Unable to open: {}", port_name).as_str());
+        options: ClientOptions,
+    ) -> Result<HostClient, ClientError> {
+        let port_name = port_name.to_string();
+        let timeout = options.timeouts.response;
+
+        Self::with_connector(
+            Box::new(move || transport::open(&port_name, baudrate, timeout)),
+            options,
+        )
+    }
+
+    /// Подключение к устройству через произвольный канал
+    ///
+    /// Переподключение для такого канала недоступно
+    pub fn with_transport<T: Transport + 'static>(
+        transport: T,
+        options: ClientOptions,
+    ) -> Result<Self, ClientError> {
+        Self::open(Box::new(transport), None, options)
+    }
+
+    /// Подключение к устройству через канал, открываемый `connector`
+    /// (в том числе повторно при переподключении)
+    pub fn with_connector(
+        mut connector: Connector,
+        options: ClientOptions,
+    ) -> Result<Self, ClientError> {
+        let transport = connector()?;
 
-        Self::try_handshake(serial_port)
+        Self::open(transport, Some(connector), options)
     }
 
-    /// Попытка установить соединение с устройством
-    fn try_handshake(instance: Box<dyn serialport::SerialPort + 'static>) -> Result<Self, String> {
-        let mut attempts: u8 = 1;
+    fn open(
+        transport: Box<dyn Transport>,
+        connector: Option<Connector>,
+        options: ClientOptions,
+    ) -> Result<Self, ClientError> {
+        let connection = Arc::new(ConnectionMonitor::new());
+        let (reader, link) = Self::attach(transport, &options, &connection)?;
 
         let mut client_connection = HostClient {
-            serial_port: instance,
+            link,
+            reader,
+            initial_checksum: options.checksum,
+            options,
+            device_info: None,
+            connection,
+            heartbeat: None,
+            connector,
+        };
+
+        client_connection.handshake()?;
+        Ok(client_connection)
+    }
+
+    /// Запуск потока чтения для открытого канала
+    fn attach(
+        transport: Box<dyn Transport>,
+        options: &ClientOptions,
+        connection: &Arc<ConnectionMonitor>,
+    ) -> Result<(BackgroundReader, Arc<Mutex<Link>>), ClientError> {
+        let (reader, replies) = BackgroundReader::spawn(
+            transport.try_clone()?,
+            options.timeouts.inter_byte,
+            options.checksum,
+            connection.clone(),
+        )?;
+
+        let link = Link {
+            name: transport.name(),
+            transport: Some(transport),
+            replies,
+            next_id: None,
         };
 
+        Ok((reader, Arc::new(Mutex::new(link))))
+    }
+
+    fn link(&self) -> MutexGuard<'_, Link> {
+        lock(&self.link)
+    }
+
+    pub fn get_timeouts(&self) -> ResponseTimeouts {
+        self.options.timeouts
+    }
+
+    /// Изменение параметров ожидания ответа
+    pub fn set_timeouts(&mut self, timeouts: ResponseTimeouts) {
+        self.options.timeouts = timeouts;
+        self.reader.shared().set_inter_byte(timeouts.inter_byte);
+    }
+
+    pub fn get_retry_policy(&self) -> RetryPolicy {
+        self.options.retry.clone()
+    }
+
+    /// Изменение политики повторов запросов
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.options.retry = retry;
+    }
+
+    /// Текущий алгоритм контрольной суммы пакетов
+    pub fn get_checksum(&self) -> Checksum {
+        self.options.checksum
+    }
+
+    /// Переход устройства и клиента на другой алгоритм контрольной суммы
+    ///
+    /// Устройство подтверждает команду со старым алгоритмом. Поток чтения
+    /// переключается сразу после ответа, при отказе алгоритм восстанавливается
+    pub fn set_checksum(&mut self, checksum: Checksum) -> Result<(), ClientError> {
+        let shared = self.reader.shared();
+        shared.router().switch_checksum_on_reply(Some(checksum));
+
+        let result = self.execute(&Command::SetChecksum(checksum));
+
+        let shared = self.reader.shared();
+        shared.router().switch_checksum_on_reply(None);
+        match result {
+            Ok(_) => {
+                self.options.checksum = checksum;
+                info!("Checksum switched to {}", checksum);
+                Ok(())
+            }
+            Err(e) => {
+                shared.set_checksum(self.options.checksum);
+                Err(e)
+            }
+        }
+    }
+
+    /// Текущее состояние соединения с устройством
+    pub fn get_connection_state(&self) -> ConnectionState {
+        self.connection.get()
+    }
+
+    /// Подписка на смену состояния соединения
+    ///
+    /// Подписка сохраняется при переподключении
+    pub fn subscribe_state(&mut self) -> Receiver<ConnectionState> {
+        self.connection.subscribe()
+    }
+
+    /// Установка соединения: приветствие, опрос устройства, согласование
+    /// нумерации и контрольной суммы, запуск контроля связи
+    ///
+    /// Выполняется в состоянии `Connecting`
+    fn handshake(&mut self) -> Result<(), ClientError> {
+        match self.try_handshake() {
+            Ok(()) => {
+                // Не меняет состояние, если канал закрылся сразу после приветствия
+                self.connection.set(ConnectionState::Connected);
+                self.start_heartbeat()
+            }
+            Err(e) => {
+                self.connection.set(ConnectionState::Lost);
+                Err(e)
+            }
+        }
+    }
+
+    /// Попытка установить соединение с устройством
+    fn try_handshake(&mut self) -> Result<(), ClientError> {
+        let policy = self.options.handshake.clone();
+
+        let started = Instant::now();
+        let mut attempts: u8 = 0;
+
         // Цикл попыток установить соединение
-        'handshake_loop: loop {
-            warn!("Handshake attempt: {}", attempts);
+        while let Some(delay) = policy.retry.next_attempt(attempts, started.elapsed()) {
+            thread::sleep(delay);
+            attempts += 1;
 
-            let answer = client_connection.send_request("hello");
+            warn!("Handshake attempt: {} ({})", attempts, self.link().name);
 
-            if let Ok(response) = answer {
-                warn!("Responce from device: {}", response);
+            let answer = Self::console_message(&policy.request)
+                .and_then(|message| self.exchange(&message))
+                .and_then(|message| Ok(String::from_utf8(message.get_data().to_vec())?));
 
-                if response.as_bytes() == b"Hi!\r\n" {
-                    return Ok(client_connection);
+            match answer {
+                Ok(response) => {
+                    warn!("Responce from device: {}", response);
+
+                    if policy.reply.matches(&response) {
+                        if policy.query_info {
+                            self.identify()?;
+                        }
+                        if policy.sequence_ids {
+                            self.enable_sequence_ids();
+                        }
+                        if let Some(checksum) = policy.checksum {
+                            self.negotiate_checksum(checksum)?;
+                        }
+                        return Ok(());
+                    }
                 }
+                Err(e) => warn!("Handshake error: {}", e),
             }
+        }
 
+        Err(ClientError::HandshakeFailed { attempts })
+    }
+
+    /// Запуск потока контроля связи (если он включен в настройках)
+    ///
+    /// Проверочным запросом служит запрос приветствия
+    fn start_heartbeat(&mut self) -> Result<(), ClientError> {
+        if let Some(policy) = self.options.heartbeat.clone() {
+            self.heartbeat = Some(Heartbeat::spawn(
+                policy,
+                Self::console_message(&self.options.handshake.request)?,
+                self.options.timeouts.response,
+                self.link.clone(),
+                self.reader.shared().clone(),
+                self.connection.clone(),
+            )?);
+        }
+
+        Ok(())
+    }
+
+    /// Переподключение к устройству: канал открывается заново,
+    /// приветствие и согласование повторяются
+    ///
+    /// Обработчики сообщений переносятся в новое соединение, подписки
+    /// (`subscribe`) закрываются вместе со старым каналом
+    pub fn reconnect(&mut self) -> Result<(), ClientError> {
+        let Some(connector) = self.connector.as_mut() else {
+            return Err(ClientError::ReconnectUnavailable);
+        };
+
+        warn!("Reconnecting to {}", lock(&self.link).name);
+        self.connection.set(ConnectionState::Connecting);
+        self.heartbeat = None;
+
+        // Старый канал закрывается до открытия нового (порт может открываться монопольно)
+        let handlers = self.reader.shared().router().take_handlers();
+        self.reader.stop();
+        lock(&self.link).close();
+
+        self.options.checksum = self.initial_checksum;
+        let attached = connector()
+            .and_then(|transport| Self::attach(transport, &self.options, &self.connection));
+
+        match attached {
+            Ok((reader, link)) => {
+                self.reader = reader;
+                self.link = link;
+                self.device_info = None;
+            }
+            Err(e) => {
+                self.connection.set(ConnectionState::Lost);
+                self.reader.shared().router().restore_handlers(handlers);
+                return Err(e);
+            }
+        }
+        self.reader.shared().router().restore_handlers(handlers);
+
+        self.handshake()
+    }
+
+    /// Автоматическое переподключение после потери связи
+    /// (`HeartbeatPolicy::reconnect`)
+    fn restore_connection(&mut self) -> Result<(), ClientError> {
+        let reconnect = self
+            .options
+            .heartbeat
+            .as_ref()
+            .is_some_and(|policy| policy.reconnect);
+
+        if reconnect && self.connector.is_some() && self.connection.get() == ConnectionState::Lost {
+            self.reconnect()?;
+        }
+
+        Ok(())
+    }
+
+    /// Сведения об устройстве, полученные при подключении
+    ///
+    /// `None`, если запрос отключен или устройство его не поддерживает
+    pub fn get_device_info(&self) -> Option<&DeviceInfo> {
+        self.device_info.as_ref()
+    }
+
+    /// Запрос сведений об устройстве
+    pub fn query_device_info(&mut self) -> Result<DeviceInfo, ClientError> {
+        let command = Command::Info;
+
+        match self.execute(&command)? {
+            Response::Info(info) => {
+                self.device_info = Some(info.clone());
+                Ok(info)
+            }
+            response => Err(ClientError::UnexpectedReply {
+                request: command.to_string(),
+                reply: response.to_string(),
+            }),
+        }
+    }
+
+    /// Опрос устройства после приветствия и проверка версии протокола
+    ///
+    /// Прошивки без `get server_info` не считаются ошибкой
+    fn identify(&mut self) -> Result<(), ClientError> {
+        match self.query_device_info() {
+            Ok(info) if !info.is_compatible() => Err(ClientError::IncompatibleProtocol {
+                device: info.protocol_version,
+                host: PROTOCOL_VERSION,
+            }),
+            Ok(info) => {
+                info!("Connected to {}", info);
+                Ok(())
+            }
+            Err(
+                e @ (ClientError::UnexpectedReply { .. }
+                | ClientError::Rejected { .. }
+                | ClientError::Timeout),
+            ) => {
+                warn!("Device info is not available: {}", e);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Включение нумерации запросов, если устройство ее поддерживает
+    fn enable_sequence_ids(&mut self) {
+        if self
+            .device_info
+            .as_ref()
+            .is_some_and(|info| info.supports_capability(SEQUENCE_CAPABILITY))
+        {
+            info!("Request sequence numbers enabled");
+            self.link().next_id = Some(0);
+        }
+    }
+
+    /// Нумерация запросов включена
+    pub fn uses_sequence_ids(&self) -> bool {
+        self.link().next_id.is_some()
+    }
+
+    /// Согласование алгоритма контрольной суммы после приветствия
+    ///
+    /// Переход выполняется, только если устройство сообщило
+    /// о поддержке алгоритма в `DeviceInfo`
+    fn negotiate_checksum(&mut self, checksum: Checksum) -> Result<(), ClientError> {
+        if checksum == self.options.checksum {
+            return Ok(());
+        }
+
+        match &self.device_info {
+            Some(info) if info.supports_checksum(checksum) => self.set_checksum(checksum),
+            _ => {
+                warn!(
+                    "Checksum {} is not supported by the device, keeping {}",
+                    checksum, self.options.checksum
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Регистрация обработчика сообщений с заданным опкодом
+    ///
+    /// Сообщения, не являющиеся ответами на запросы, передаются обработчику
+    /// своего опкода. Обработчик вызывается из потока чтения.
+    /// Возвращает замененный обработчик
+    pub fn register_handler<F>(&mut self, opcode: Opcode, handler: F) -> Option<MessageHandler>
+    where
+        F: FnMut(&Message) + Send + 'static,
+    {
+        self.reader
+            .shared()
+            .router()
+            .register_handler(opcode, Box::new(handler))
+    }
+
+    /// Удаление обработчика сообщений с заданным опкодом
+    pub fn remove_handler(&mut self, opcode: Opcode) -> Option<MessageHandler> {
+        self.reader.shared().router().remove_handler(opcode)
+    }
+
+    /// Подписка на сообщения, не являющиеся ответами на запросы
+    ///
+    /// Приемник отключается при закрытии соединения или удалении клиента
+    pub fn subscribe(&mut self) -> Receiver<Message> {
+        self.reader.shared().router().subscribe()
+    }
+
+    /// Соединение с устройством активно (поток чтения работает
+    /// и связь не потеряна)
+    pub fn is_connected(&self) -> bool {
+        self.reader.is_running() && self.connection.get() != ConnectionState::Lost
+    }
+
+    /// Отправка сообщения и ожидание ответа с тем же опкодом
+    ///
+    /// Длинные сообщения передаются и принимаются фрагментами.
+    /// Запрос, оставшийся без ответа, повторяется согласно `ClientOptions::retry`,
+    /// при нумерации запросов повтор отправляется с новым номером.
+    /// После потери связи запросу предшествует переподключение
+    pub fn send_message(&mut self, message: Message) -> Result<Message, ClientError> {
+        self.restore_connection()?;
+
+        let retry = self.options.retry.clone();
+        let started = Instant::now();
+        let mut attempts: u8 = 0;
+        let mut last_error = ClientError::Timeout;
+
+        while let Some(delay) = retry.next_attempt(attempts, started.elapsed()) {
+            if attempts > 0 {
+                warn!(
+                    "Retrying request (attempt {}): {}",
+                    attempts + 1,
+                    last_error
+                );
+                thread::sleep(delay);
+                self.link().discard_input();
+            }
             attempts += 1;
 
-            if attempts > 3 {
-                break 'handshake_loop;
+            match self.exchange(&message) {
+                Ok(reply) => return Ok(reply),
+                Err(e) if e.is_retryable() => last_error = e,
+                Err(e) => return Err(e),
             }
         }
-        return Err("Handshake failed!".to_string());
+
+        Err(last_error)
+    }
+
+    /// Однократная отправка сообщения и ожидание ответа (`Link::exchange`)
+    fn exchange(&mut self, message: &Message) -> Result<Message, ClientError> {
+        lock(&self.link).exchange(
+            self.reader.shared(),
+            message,
+            self.options.timeouts.response,
+        )
     }
 
-    /// Отправка запроса на устройство
-    pub fn send_request(&mut self, request: &str) -> Result<String, String> {
-        let mut frame = MUFrame::new();
+    /// Отправка нескольких сообщений без ожидания ответа на каждое (конвейер)
+    ///
+    /// Ответы сопоставляются с запросами по номерам и возвращаются в порядке
+    /// запросов. Одновременно ожидается не более `PIPELINE_DEPTH` ответов,
+    /// запросы без ответа повторяются согласно `ClientOptions::retry`.
+    /// Без нумерации запросов сообщения отправляются по одному
+    pub fn send_messages(&mut self, messages: &[Message]) -> Result<Vec<Message>, ClientError> {
+        self.restore_connection()?;
+
+        if !self.uses_sequence_ids() {
+            return messages
+                .iter()
+                .map(|message| self.send_message(message.clone()))
+                .collect();
+        }
+
+        let mut replies = Vec::with_capacity(messages.len());
+        for window in messages.chunks(PIPELINE_DEPTH) {
+            replies.extend(self.send_window(window)?);
+        }
+
+        Ok(replies)
+    }
+
+    /// Отправка группы нумерованных сообщений с повторами
+    fn send_window(&mut self, messages: &[Message]) -> Result<Vec<Message>, ClientError> {
+        let retry = self.options.retry.clone();
+        let started = Instant::now();
+        let mut attempts: u8 = 0;
+        let mut last_error = ClientError::Timeout;
+        let mut replies = vec![None; messages.len()];
+
+        while let Some(delay) = retry.next_attempt(attempts, started.elapsed()) {
+            if attempts > 0 {
+                warn!(
+                    "Retrying {} unanswered requests (attempt {}): {}",
+                    replies.iter().filter(|reply| reply.is_none()).count(),
+                    attempts + 1,
+                    last_error
+                );
+                thread::sleep(delay);
+            }
+            attempts += 1;
+
+            let exchanged = lock(&self.link).exchange_pipelined(
+                self.reader.shared(),
+                messages,
+                &mut replies,
+                self.options.timeouts.response,
+            );
+            match exchanged {
+                Ok(()) => return Ok(replies.into_iter().flatten().collect()),
+                Err(e) if e.is_retryable() => last_error = e,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Сообщение текстовой консоли с завершающим переводом строки
+    fn console_message(request: &str) -> Result<Message, ClientError> {
+        Ok(Message::new(
+            Opcode::Console,
+            format!("{}{}", request, "\n").into_bytes(),
+        )?)
+    }
+
+    /// Отправка запроса текстовой консоли на устройство
+    ///
+    /// Ответ, переданный несколькими фрагментами, возвращается целиком
+    pub fn send_request(&mut self, request: &str) -> Result<String, ClientError> {
+        let reply = self.send_message(Self::console_message(request)?)?;
+
+        Ok(String::from_utf8(reply.get_data().to_vec())?)
+    }
+
+    /// Выполнение команды текстовой консоли
+    ///
+    /// Отказ устройства (`ERR <code>`) возвращается как `ClientError::Rejected`
+    pub fn execute(&mut self, command: &Command) -> Result<Response, ClientError> {
+        let reply = self.send_request(&command.to_string())?;
+
+        Self::check_response(command, &reply)
+    }
+
+    /// Выполнение нескольких команд конвейером (`send_messages`)
+    ///
+    /// Ошибки обмена прерывают выполнение, результат разбора ответа
+    /// (в том числе отказ устройства) возвращается для каждой команды
+    pub fn execute_all(
+        &mut self,
+        commands: &[Command],
+    ) -> Result<Vec<Result<Response, ClientError>>, ClientError> {
+        let messages = commands
+            .iter()
+            .map(|command| Self::console_message(&command.to_string()))
+            .collect::<Result<Vec<Message>, ClientError>>()?;
+
+        let replies = self.send_messages(&messages)?;
+
+        Ok(commands
+            .iter()
+            .zip(replies)
+            .map(|(command, reply)| {
+                let reply = String::from_utf8(reply.get_data().to_vec())?;
+                Self::check_response(command, &reply)
+            })
+            .collect())
+    }
+
+    /// Разбор ответа на команду, отказ устройства возвращается как ошибка
+    fn check_response(command: &Command, reply: &str) -> Result<Response, ClientError> {
+        match command.parse_response(reply)? {
+            Response::Error(code) => Err(ClientError::Rejected {
+                request: command.to_string(),
+                code,
+            }),
+            response => Ok(response),
+        }
+    }
+
+    /// Чтение параметра устройства
+    pub fn get_parameter(&mut self, parameter: &str) -> Result<u32, ClientError> {
+        let command = Command::Get(parameter.to_string());
+
+        match self.execute(&command)? {
+            Response::Value { value, .. } => Ok(value),
+            response => Err(ClientError::UnexpectedReply {
+                request: command.to_string(),
+                reply: response.to_string(),
+            }),
+        }
+    }
+
+    /// Запись параметра устройства
+    pub fn set_parameter(&mut self, parameter: &str, value: u32) -> Result<(), ClientError> {
+        self.execute(&Command::Set(parameter.to_string(), value))?;
+        Ok(())
+    }
+
+    /// Чтение нескольких параметров устройства конвейером
+    ///
+    /// Значения возвращаются в порядке имен параметров
+    pub fn get_parameters(&mut self, parameters: &[&str]) -> Result<Vec<u32>, ClientError> {
+        let commands = parameters
+            .iter()
+            .map(|parameter| Command::Get(parameter.to_string()))
+            .collect::<Vec<Command>>();
+
+        commands
+            .iter()
+            .zip(self.execute_all(&commands)?)
+            .map(|(command, response)| match response? {
+                Response::Value { value, .. } => Ok(value),
+                response => Err(ClientError::UnexpectedReply {
+                    request: command.to_string(),
+                    reply: response.to_string(),
+                }),
+            })
+            .collect()
+    }
+
+    /// Запись нескольких параметров устройства конвейером
+    ///
+    /// Отказ устройства возвращается для каждого параметра отдельно
+    pub fn set_parameters(
+        &mut self,
+        values: &[(&str, u32)],
+    ) -> Result<Vec<Result<(), ClientError>>, ClientError> {
+        let commands = values
+            .iter()
+            .map(|(parameter, value)| Command::Set(parameter.to_string(), *value))
+            .collect::<Vec<Command>>();
+
+        Ok(self
+            .execute_all(&commands)?
+            .into_iter()
+            .map(|response| response.map(|_| ()))
+            .collect())
+    }
+
+    /// Выбор режима стриминга
+    ///
+    /// Ответ `OK` обязателен, только если устройство сообщило `ACK_CAPABILITY`,
+    /// иначе подходит любой ответ, кроме отказа `ERR <code>`
+    pub fn set_streaming_mode(&mut self, mode: u8) -> Result<(), ClientError> {
+        let command = Command::SetMode(mode);
+
+        if self
+            .device_info
+            .as_ref()
+            .is_some_and(|info| info.supports_capability(ACK_CAPABILITY))
+        {
+            self.execute(&command)?;
+            return Ok(());
+        }
+
+        let reply = self.send_request(&command.to_string())?;
+        match Self::check_response(&command, &reply) {
+            Err(error @ ClientError::Rejected { .. }) => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    /// Фиксация записанных параметров (для прошивок с `COMMIT_CAPABILITY`)
+    pub fn commit(&mut self) -> Result<(), ClientError> {
+        self.execute(&Command::Commit)?;
+        Ok(())
+    }
+
+    /// Чтение журнала устройства
+    pub fn read_log(&mut self) -> Result<Vec<String>, ClientError> {
+        let command = Command::Log;
+
+        match self.execute(&command)? {
+            Response::Log(lines) => Ok(lines),
+            response => Err(ClientError::UnexpectedReply {
+                request: command.to_string(),
+                reply: response.to_string(),
+            }),
+        }
+    }
+
+    /// Запрос текущего состояния лифта (режим OnDemand)
+    ///
+    /// С нумерацией запросов ответ сопоставляется по номеру: события, переданные
+    /// устройством между запросом и ответом, достаются обработчикам и подписчикам.
+    /// Без нумерации ответом считается первый пакет `Streaming` после запроса,
+    /// поэтому опрос однозначен, только пока устройство не передает данные само
+    /// (режимы Silent и OnDemand)
+    pub fn poll_status(&mut self) -> Result<DeviceEvent, ClientError> {
+        let request = Message::new(Opcode::Streaming, vec![STATUS_REQUEST])?;
+
+        let reply = self.send_message(request)?;
+        Ok(DeviceEvent::from_payload(reply.get_data())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::FrameDecoder;
+    use crate::fragment::Reassembler;
+    use crate::mu_frame::MUFrame;
+    use crate::options::{HandshakePolicy, HeartbeatMode, HeartbeatPolicy, ReplyMatcher};
+    use crate::transport::MemoryPipe;
+    use std::io::{Read, Write};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    /// Канал модели устройства
+    struct Device {
+        pipe: MemoryPipe,
+        decoder: FrameDecoder,
+    }
+
+    impl Device {
+        /// Передача пакета без изменений
+        fn send_frame(&mut self, frame: &MUFrame) {
+            self.pipe.write_all(&frame.serialize()).unwrap();
+        }
+
+        /// Передача сообщения с текущим алгоритмом контрольной суммы
+        fn send(&mut self, message: &Message) {
+            for frame in message.to_frames(self.decoder.get_checksum()).unwrap() {
+                self.send_frame(&frame);
+            }
+        }
+
+        /// Консольный ответ с номером запроса (если запрос его содержит)
+        fn reply(&mut self, request: &Message, text: &[u8]) {
+            let mut reply = Message::new(Opcode::Console, text.to_vec()).unwrap();
+            if let Some(id) = request.get_id() {
+                reply = reply.with_id(id);
+            }
+            self.send(&reply);
+        }
+
+        /// Смена алгоритма контрольной суммы принимаемых и передаваемых пакетов
+        fn set_checksum(&mut self, checksum: Checksum) {
+            self.decoder.set_checksum(checksum);
+        }
+    }
+
+    /// Модель устройства: принимает запросы и передает их обработчику `handle`
+    ///
+    /// Обработчик отвечает через `Device` и возвращает `false`, чтобы закрыть канал.
+    /// Состояние `state` доступно обработчику и возвращается по завершении работы
+    fn spawn_device_with<S, F>(
+        pipe: MemoryPipe,
+        mut state: S,
+        mut handle: F,
+    ) -> thread::JoinHandle<S>
+    where
+        S: Send + 'static,
+        F: FnMut(&mut S, &mut Device, Message) -> bool + Send + 'static,
+    {
+        thread::spawn(move || {
+            let mut device = Device {
+                pipe,
+                decoder: FrameDecoder::new(),
+            };
+            let mut reassembler = Reassembler::new();
+            let mut buf = [0; 64];
+            loop {
+                let size = match device.pipe.read(&mut buf) {
+                    Ok(0) => return state,
+                    Ok(size) => size,
+                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                    Err(_) => return state,
+                };
+                device.decoder.push(&buf[..size]);
+
+                while let Some(frame) = device.decoder.next_frame() {
+                    let Some(request) = reassembler.push(frame).unwrap() else {
+                        continue;
+                    };
+                    if !handle(&mut state, &mut device, request) {
+                        return state;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Простейшая модель устройства: отвечает на запросы по таблице
+    fn spawn_device(
+        pipe: MemoryPipe,
+        answers: Vec<(&'static str, Vec<MUFrame>)>,
+    ) -> thread::JoinHandle<()> {
+        spawn_device_with(pipe, (), move |_, device, request| {
+            let request = String::from_utf8(request.get_data().to_vec()).unwrap();
+            for (expected, replies) in &answers {
+                if request.trim_end() == *expected {
+                    for reply in replies {
+                        device.send_frame(reply);
+                    }
+                }
+            }
+            true
+        })
+    }
+
+    fn test_options() -> ClientOptions {
+        ClientOptions {
+            timeouts: ResponseTimeouts {
+                response: Duration::from_millis(200),
+                inter_byte: Duration::from_millis(50),
+            },
+            handshake: HandshakePolicy {
+                retry: RetryPolicy {
+                    backoff: Duration::from_millis(10),
+                    ..HandshakePolicy::default().retry
+                },
+                query_info: false,
+                ..HandshakePolicy::default()
+            },
+            retry: RetryPolicy::none(),
+            checksum: Checksum::Crc8,
+            heartbeat: None,
+        }
+    }
+
+    fn frame(opcode: Opcode, data: &[u8]) -> MUFrame {
+        let mut frame = MUFrame::with_opcode(opcode);
+        frame.set_data(data.to_vec()).unwrap();
         frame
-            .set_data(format!("{}{}", request, "\n").as_bytes().to_vec())
-            .map_err(|e| e.to_string())?;
-        crate::send_proto_message(frame, &mut self.serial_port)?;
+    }
+
+    #[test]
+    fn test_handshake_and_request() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get groupnumber",
+                    vec![frame(Opcode::Console, b"groupnumber:3\r\n")],
+                ),
+            ],
+        );
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        assert_eq!(
+            client.send_request("get groupnumber").unwrap(),
+            "groupnumber:3\r\n"
+        );
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_handshake_failure() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(50));
+        let device = spawn_device(
+            device,
+            vec![("hello", vec![frame(Opcode::Console, b"?\r\n")])],
+        );
+
+        let result = HostClient::with_transport(host, test_options());
+        assert!(matches!(
+            result,
+            Err(ClientError::HandshakeFailed { attempts: 3 })
+        ));
+
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_dispatch_by_opcode() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get musicvolume",
+                    vec![
+                        frame(Opcode::Streaming, &[0x01, 0x02]),
+                        frame(Opcode::Console, b"musicvolume:2\r\n"),
+                    ],
+                ),
+            ],
+        );
+
+        let streamed = Arc::new(Mutex::new(Vec::new()));
+        let streamed_hold = streamed.clone();
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        client.register_handler(Opcode::Streaming, move |frame| {
+            streamed_hold.lock().unwrap().push(frame.clone());
+        });
+
+        assert_eq!(
+            client.send_request("get musicvolume").unwrap(),
+            "musicvolume:2\r\n"
+        );
+        assert_eq!(
+            *streamed.lock().unwrap(),
+            vec![Message::from(frame(Opcode::Streaming, &[0x01, 0x02]))]
+        );
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_custom_handshake() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![("ping", vec![frame(Opcode::Console, b"MU-2 ready\r\n")])],
+        );
+
+        let mut options = test_options();
+        options.handshake.request = "ping".to_string();
+        options.handshake.reply = ReplyMatcher::from("prefix:MU-");
+
+        let client = HostClient::with_transport(host, options).unwrap();
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_retry_after_lost_reply() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+
+        // Устройство теряет первый запрос "get" и отвечает на повтор
+        let device = spawn_device_with(device, 0, |requests, device, request| {
+            if request.get_data().as_slice() == b"hello\n" {
+                device.reply(&request, b"Hi!\r\n");
+            } else {
+                *requests += 1;
+                if *requests > 1 {
+                    device.reply(&request, b"soundvolume:2\r\n");
+                }
+            }
+            true
+        });
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        client.set_retry_policy(RetryPolicy {
+            attempts: 2,
+            backoff: Duration::from_millis(10),
+            backoff_factor: 1,
+            total_timeout: None,
+        });
+        assert_eq!(
+            client.send_request("get soundvolume").unwrap(),
+            "soundvolume:2\r\n"
+        );
+
+        drop(client);
+        assert_eq!(device.join().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_device_info_on_connect() {
+        let info = "model=MU-2;fw=1.4.0;proto=1.2;sn=000123;params=groupnumber";
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get server_info",
+                    vec![frame(Opcode::Console, format!("{}\r\n", info).as_bytes())],
+                ),
+            ],
+        );
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+
+        let client = HostClient::with_transport(host, options).unwrap();
+        assert_eq!(client.get_device_info(), Some(&info.parse().unwrap()));
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_device_info_unsupported() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get server_info",
+                    vec![frame(Opcode::Console, b"ERR 1\r\n")],
+                ),
+            ],
+        );
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+
+        let client = HostClient::with_transport(host, options).unwrap();
+        assert_eq!(client.get_device_info(), None);
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_incompatible_protocol() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get server_info",
+                    vec![frame(Opcode::Console, b"model=MU-3;fw=3.0;proto=2.0\r\n")],
+                ),
+            ],
+        );
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+
+        let result = HostClient::with_transport(host, options);
+        assert!(matches!(
+            result,
+            Err(ClientError::IncompatibleProtocol { device, .. }) if device.major == 2
+        ));
+
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_typed_commands() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get loadcapacity",
+                    vec![frame(Opcode::Console, b"loadcapacity:7\r\n")],
+                ),
+                (
+                    "set loadcapacity 9",
+                    vec![frame(Opcode::Console, b"OK\r\n")],
+                ),
+                (
+                    "set groupnumber 99",
+                    vec![frame(Opcode::Console, b"ERR 3\r\n")],
+                ),
+            ],
+        );
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        assert_eq!(client.get_parameter("loadcapacity").unwrap(), 7);
+        client.set_parameter("loadcapacity", 9).unwrap();
+        assert!(matches!(
+            client.set_parameter("groupnumber", 99),
+            Err(ClientError::Rejected { code: 3, .. })
+        ));
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_legacy_mode_reply() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                ("set mode 1", vec![frame(Opcode::Console, b"mode:1\r\n")]),
+                ("set mode 3", vec![frame(Opcode::Console, b"ERR 2\r\n")]),
+            ],
+        );
+
+        // Устройство без сведений о себе: подходит любой ответ, кроме отказа
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        client.set_streaming_mode(1).unwrap();
+        assert!(matches!(
+            client.set_streaming_mode(3),
+            Err(ClientError::Rejected { code: 2, .. })
+        ));
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_acknowledged_mode_reply() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get server_info",
+                    vec![frame(
+                        Opcode::Console,
+                        b"model=MU-4;fw=2.0;proto=1.1;caps=ack\r\n",
+                    )],
+                ),
+                ("set mode 1", vec![frame(Opcode::Console, b"OK\r\n")]),
+                ("set mode 2", vec![frame(Opcode::Console, b"mode:2\r\n")]),
+            ],
+        );
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+
+        let mut client = HostClient::with_transport(host, options).unwrap();
+        client.set_streaming_mode(1).unwrap();
+        assert!(matches!(
+            client.set_streaming_mode(2),
+            Err(ClientError::UnexpectedReply { .. })
+        ));
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_fragmented_exchange() {
+        let request: &'static str = format!("set note {}", "n".repeat(400)).leak();
+        let log = (0..40)
+            .map(|i| format!("#{:02} set groupnumber {}\r\n", i, i % 16))
+            .collect::<String>();
+
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    request,
+                    Message::new(Opcode::Console, log.clone().into_bytes())
+                        .unwrap()
+                        .to_frames(Checksum::Crc8)
+                        .unwrap(),
+                ),
+            ],
+        );
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        assert_eq!(client.send_request(request).unwrap(), log);
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_stale_reply_discarded() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+
+        // Устройство отвечает на первый запрос "get" после таймаута клиента,
+        // на повтор - сразу. Ответы повторяют номер запроса
+        let device = spawn_device_with(device, Vec::new(), |ids, device, request| {
+            match request.get_data().as_slice() {
+                b"hello\n" => device.reply(&request, b"Hi!\r\n"),
+                b"get server_info\n" => {
+                    device.reply(&request, b"model=MU-4;fw=2.0;proto=1.1;caps=seq\r\n")
+                }
+                _ => {
+                    ids.push(request.get_id());
+                    if ids.len() == 1 {
+                        thread::sleep(Duration::from_millis(300));
+                        device.reply(&request, b"soundvolume:1\r\n");
+                    } else {
+                        device.reply(&request, b"soundvolume:2\r\n");
+                    }
+                }
+            }
+            true
+        });
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+        options.handshake.sequence_ids = true;
+        options.retry = RetryPolicy {
+            attempts: 2,
+            backoff: Duration::from_millis(10),
+            backoff_factor: 1,
+            total_timeout: None,
+        };
+
+        let mut client = HostClient::with_transport(host, options).unwrap();
+        assert!(client.uses_sequence_ids());
+        assert_eq!(
+            client.send_request("get soundvolume").unwrap(),
+            "soundvolume:2\r\n"
+        );
+
+        drop(client);
+        let ids = device.join().unwrap();
+        assert_eq!(ids.len(), 2);
+        assert!(ids[0].is_some() && ids[0] != ids[1]);
+    }
+
+    #[test]
+    fn test_pipelined_parameters() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+
+        // Устройство отвечает, только получив все три запроса, в обратном порядке
+        let device = spawn_device_with(device, Vec::new(), |batch, device, request| {
+            let text = String::from_utf8(request.get_data().to_vec()).unwrap();
+            let reply = match text.trim_end() {
+                "hello" => "Hi!\r\n".to_string(),
+                "get server_info" => "model=MU-4;fw=2.0;proto=1.1;caps=seq\r\n".to_string(),
+                "get musicvolume" => "ERR 2\r\n".to_string(),
+                request => match request.split_whitespace().collect::<Vec<&str>>()[..] {
+                    ["get", name] => format!("{}:{}\r\n", name, name.len()),
+                    _ => "OK\r\n".to_string(),
+                },
+            };
+
+            let reply = Message::new(Opcode::Console, reply.into_bytes()).unwrap();
+            match request.get_id() {
+                Some(id) => batch.push(reply.with_id(id)),
+                None => batch.push(reply),
+            }
+            if request.get_id().is_some() && batch.len() < 3 {
+                return true;
+            }
+            for reply in batch.drain(..).rev() {
+                device.send(&reply);
+            }
+            true
+        });
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+        options.handshake.sequence_ids = true;
+
+        let mut client = HostClient::with_transport(host, options).unwrap();
+        assert_eq!(
+            client
+                .get_parameters(&["groupnumber", "loadcapacity", "soundvolume"])
+                .unwrap(),
+            vec![11, 12, 11]
+        );
+
+        let results = client
+            .execute_all(&[
+                Command::Set("groupnumber".to_string(), 1),
+                Command::Get("musicvolume".to_string()),
+                Command::Commit,
+            ])
+            .unwrap();
+        assert!(matches!(results[0], Ok(Response::Ack)));
+        assert!(matches!(
+            results[1],
+            Err(ClientError::Rejected { code: 2, .. })
+        ));
+        assert!(matches!(results[2], Ok(Response::Ack)));
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_batch_without_sequence_ids() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                ("set groupnumber 4", vec![frame(Opcode::Console, b"OK\r\n")]),
+                (
+                    "set musicvolume 9",
+                    vec![frame(Opcode::Console, b"ERR 3\r\n")],
+                ),
+            ],
+        );
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        assert!(!client.uses_sequence_ids());
+
+        let results = client
+            .set_parameters(&[("groupnumber", 4), ("musicvolume", 9)])
+            .unwrap();
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(ClientError::Rejected { code: 3, .. })
+        ));
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_checksum_negotiation() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+
+        // Устройство переходит на CRC-16 после подтверждения `set checksum`
+        let device = spawn_device_with(device, Checksum::Crc8, |checksum, device, request| {
+            let reply: &[u8] = match request.get_data().as_slice() {
+                b"hello\n" => b"Hi!\r\n",
+                b"get server_info\n" => b"model=MU-4;fw=2.0;proto=1.1;crc=crc8,crc16\r\n",
+                b"set checksum crc16\n" => b"OK\r\n",
+                _ => b"groupnumber:6\r\n",
+            };
+            device.reply(&request, reply);
+
+            if request.get_data().starts_with(b"set checksum") {
+                *checksum = Checksum::Crc16;
+                device.set_checksum(*checksum);
+            }
+            true
+        });
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+        options.handshake.checksum = Some(Checksum::Crc16);
+
+        let mut client = HostClient::with_transport(host, options).unwrap();
+        assert_eq!(client.get_checksum(), Checksum::Crc16);
+        assert_eq!(
+            client.send_request("get groupnumber").unwrap(),
+            "groupnumber:6\r\n"
+        );
+
+        drop(client);
+        assert_eq!(device.join().unwrap(), Checksum::Crc16);
+    }
+
+    #[test]
+    fn test_unsupported_checksum_kept() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get server_info",
+                    vec![frame(Opcode::Console, b"model=MU-2;fw=1.4;proto=1.0\r\n")],
+                ),
+            ],
+        );
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+        options.handshake.checksum = Some(Checksum::Xor);
+
+        let client = HostClient::with_transport(host, options).unwrap();
+        assert_eq!(client.get_checksum(), Checksum::Crc8);
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_poll_status() {
+        let event = DeviceEvent {
+            floor: 4,
+            door: crate::event::DoorState::Open,
+            arrival_gong: true,
+            ..Default::default()
+        };
+
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                // Запрос состояния: пакет Streaming с байтом STATUS_REQUEST
+                ("\u{1}", vec![frame(Opcode::Streaming, &event.to_payload())]),
+            ],
+        );
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        assert_eq!(client.poll_status().unwrap(), event);
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_poll_status_with_streaming_event() {
+        let unsolicited = DeviceEvent {
+            floor: 2,
+            ..Default::default()
+        };
+        let status = DeviceEvent {
+            floor: 3,
+            ..Default::default()
+        };
+
+        // Устройство передает событие между запросом состояния и ответом на него
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let event = unsolicited.clone();
+        let reply = status.clone();
+        let device = spawn_device_with(device, (), move |_, device, request| {
+            match request.get_data().as_slice() {
+                b"hello\n" => device.reply(&request, b"Hi!\r\n"),
+                b"get server_info\n" => {
+                    device.reply(&request, b"model=MU-4;fw=2.0;proto=1.1;caps=seq\r\n")
+                }
+                [STATUS_REQUEST] => {
+                    device.send(&Message::new(Opcode::Streaming, event.to_payload()).unwrap());
+                    let id = request.get_id().unwrap();
+                    device.send(
+                        &Message::new(Opcode::Streaming, reply.to_payload())
+                            .unwrap()
+                            .with_id(id),
+                    );
+                }
+                _ => (),
+            }
+            true
+        });
+
+        let mut options = test_options();
+        options.handshake.query_info = true;
+        options.handshake.sequence_ids = true;
+
+        let mut client = HostClient::with_transport(host, options).unwrap();
+        let events = client.subscribe();
+        assert_eq!(client.poll_status().unwrap(), status);
+
+        let event = events.recv_timeout(Duration::from_millis(200)).unwrap();
+        assert_eq!(DeviceEvent::from_payload(event.get_data()), Ok(unsolicited));
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_subscribe_while_requesting() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+        let mut stream_writer = device.try_clone().unwrap();
+        let device = spawn_device(
+            device,
+            vec![
+                ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                (
+                    "get groupnumber",
+                    vec![frame(Opcode::Console, b"groupnumber:5\r\n")],
+                ),
+            ],
+        );
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        let events = client.subscribe();
+
+        // Потоковые данные приходят независимо от запросов
+        for value in 0..3u8 {
+            stream_writer
+                .write_all(&frame(Opcode::Streaming, &[value]).serialize())
+                .unwrap();
+            assert_eq!(
+                client.send_request("get groupnumber").unwrap(),
+                "groupnumber:5\r\n"
+            );
+        }
+
+        for value in 0..3u8 {
+            let event = events.recv_timeout(Duration::from_millis(200)).unwrap();
+            assert_eq!(event, Message::from(frame(Opcode::Streaming, &[value])));
+        }
+
+        drop(client);
+        drop(stream_writer);
+        device.join().unwrap();
+        assert!(events.recv().is_err());
+    }
+
+    #[test]
+    fn test_connection_lost() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+
+        // Устройство отвечает на приветствие и закрывает канал
+        let device = spawn_device_with(device, (), |_, device, request| {
+            device.reply(&request, b"Hi!\r\n");
+            false
+        });
+
+        let mut client = HostClient::with_transport(host, test_options()).unwrap();
+        let events = client.subscribe();
+        device.join().unwrap();
+
+        assert!(events.recv_timeout(Duration::from_millis(500)).is_err());
+        assert!(!client.is_connected());
+        assert!(matches!(
+            client.send_request("get groupnumber"),
+            Err(ClientError::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn test_heartbeat_detects_silent_device() {
+        let (host, device) = MemoryPipe::pair(Duration::from_millis(20));
+        let silent = Arc::new(AtomicBool::new(false));
+        let device_silent = silent.clone();
+
+        // Устройство перестает отвечать, но канал остается открытым
+        let device = spawn_device_with(device, (), move |_, device, request| {
+            if !device_silent.load(Ordering::Relaxed) {
+                device.reply(&request, b"Hi!\r\n");
+            }
+            true
+        });
+
+        let mut options = test_options();
+        options.timeouts.response = Duration::from_millis(30);
+        options.heartbeat = Some(HeartbeatPolicy {
+            mode: HeartbeatMode::Ping,
+            interval: Duration::from_millis(50),
+            missed_limit: 2,
+            reconnect: false,
+        });
+
+        let mut client = HostClient::with_transport(host, options).unwrap();
+        let states = client.subscribe_state();
+        assert_eq!(client.get_connection_state(), ConnectionState::Connected);
+
+        // Проверки связи проходят, пока устройство отвечает
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(client.get_connection_state(), ConnectionState::Connected);
+
+        silent.store(true, Ordering::Relaxed);
+        let timeout = Duration::from_secs(2);
+        assert_eq!(states.recv_timeout(timeout), Ok(ConnectionState::Degraded));
+        assert_eq!(states.recv_timeout(timeout), Ok(ConnectionState::Lost));
+        assert!(!client.is_connected());
+        assert!(matches!(
+            client.reconnect(),
+            Err(ClientError::ReconnectUnavailable)
+        ));
+
+        drop(client);
+        device.join().unwrap();
+    }
+
+    #[test]
+    fn test_reconnect_after_device_restart() {
+        let (devices, connected) = mpsc::channel();
+        let connector: Connector = Box::new(move || {
+            let (host, device) = MemoryPipe::pair(Duration::from_millis(200));
+            devices
+                .send(device)
+                .map_err(|_| ClientError::ConnectionClosed)?;
+            Ok(Box::new(host))
+        });
+
+        // Устройство отвечает на приветствие и перезагружается (закрывает канал),
+        // после переподключения работает штатно
+        let device = thread::spawn(move || {
+            spawn_device_with(connected.recv().unwrap(), (), |_, device, request| {
+                device.reply(&request, b"Hi!\r\n");
+                false
+            })
+            .join()
+            .unwrap();
+
+            spawn_device(
+                connected.recv().unwrap(),
+                vec![
+                    ("hello", vec![frame(Opcode::Console, b"Hi!\r\n")]),
+                    (
+                        "get groupnumber",
+                        vec![
+                            frame(Opcode::Streaming, &[0x01]),
+                            frame(Opcode::Console, b"groupnumber:5\r\n"),
+                        ],
+                    ),
+                ],
+            )
+            .join()
+            .unwrap();
+        });
+
+        let mut options = test_options();
+        options.heartbeat = Some(HeartbeatPolicy {
+            mode: HeartbeatMode::Traffic,
+            interval: Duration::from_secs(10),
+            ..HeartbeatPolicy::default()
+        });
+
+        let mut client = HostClient::with_connector(connector, options).unwrap();
+
+        let streamed = Arc::new(Mutex::new(Vec::new()));
+        let streamed_hold = streamed.clone();
+        client.register_handler(Opcode::Streaming, move |message| {
+            streamed_hold.lock().unwrap().push(message.clone());
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.is_connected() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.get_connection_state(), ConnectionState::Lost);
 
-        let new_frame =
-            crate::recv_proto_message(&mut self.serial_port).map_err(|e| e.to_string())?;
+        // Запрос выполняется после переподключения, обработчики сохраняются
+        let states = client.subscribe_state();
+        assert_eq!(
+            client.send_request("get groupnumber").unwrap(),
+            "groupnumber:5\r\n"
+        );
+        assert_eq!(
+            states.try_iter().collect::<Vec<ConnectionState>>(),
+            vec![ConnectionState::Connecting, ConnectionState::Connected]
+        );
+        assert_eq!(
+            *streamed.lock().unwrap(),
+            vec![Message::from(frame(Opcode::Streaming, &[0x01]))]
+        );
 
-        Ok(String::from_utf8(new_frame.get_data().to_vec()).map_err(|e| e.to_string())?)
+        drop(client);
+        device.join().unwrap();
     }
 }
@@ -0,0 +1,264 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::checksum::Checksum;
+use crate::device_info::{DEVICE_INFO_REQUEST, DeviceInfo};
+use crate::error::ClientError;
+use crate::options::DEFAULT_HANDSHAKE_REQUEST;
+
+/// Подтверждение выполнения команды
+const ACK_REPLY: &str = "OK";
+/// Префикс ответа с кодом ошибки
+const ERROR_PREFIX: &str = "ERR";
+/// Имя параметра режима стриминга
+const MODE_PARAMETER: &str = "mode";
+/// Имя параметра алгоритма контрольной суммы
+const CHECKSUM_PARAMETER: &str = "checksum";
+
+/// Команда текстовой консоли устройства
+///
+/// Одно описание используется и для формирования запроса (`Display`),
+/// и для его разбора на стороне устройства (`FromStr`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Приветствие: `hello`
+    Hello,
+    /// Сведения об устройстве: `get server_info`
+    Info,
+    /// Чтение параметра: `get <name>`
+    Get(String),
+    /// Запись параметра: `set <name> <value>`
+    Set(String, u32),
+    /// Выбор режима стриминга: `set mode <mode>`
+    SetMode(u8),
+    /// Смена алгоритма контрольной суммы: `set checksum <name>`.
+    /// Подтверждение передается со старым алгоритмом, следующие пакеты - с новым
+    SetChecksum(Checksum),
+    /// Сброс настроек к заводским: `reset`
+    Reset,
+    /// Фиксация записанных параметров: `commit`
+    Commit,
+    /// Журнал устройства: `log` (ответ может занимать несколько пакетов)
+    Log,
+}
+
+/// Ответ устройства на команду
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// Ответ на приветствие
+    Greeting(String),
+    /// Сведения об устройстве
+    Info(DeviceInfo),
+    /// Значение параметра: `<name>:<value>`
+    Value { parameter: String, value: u32 },
+    /// Команда выполнена: `OK`
+    Ack,
+    /// Команда отклонена: `ERR <code>`
+    Error(u8),
+    /// Строки журнала устройства
+    Log(Vec<String>),
+}
+
+impl Command {
+    /// Разбор ответа устройства на эту команду
+    ///
+    /// Ответ, не соответствующий команде, возвращается как `UnexpectedReply`
+    pub fn parse_response(&self, reply: &str) -> Result<Response, ClientError> {
+        let text = reply.trim_end_matches(['\r', '\n']);
+        let unexpected = || ClientError::UnexpectedReply {
+            request: self.to_string(),
+            reply: reply.to_string(),
+        };
+
+        if let Some(code) = text.strip_prefix(ERROR_PREFIX) {
+            return code
+                .trim()
+                .parse::<u8>()
+                .map(Response::Error)
+                .map_err(|_| unexpected());
+        }
+
+        match self {
+            Command::Hello => Ok(Response::Greeting(text.to_string())),
+            Command::Info => text.parse().map(Response::Info).map_err(|_| unexpected()),
+            Command::Get(parameter) => match text.split_once(':') {
+                Some((name, value)) if name.trim() == parameter => value
+                    .trim()
+                    .parse::<u32>()
+                    .map(|value| Response::Value {
+                        parameter: parameter.clone(),
+                        value,
+                    })
+                    .map_err(|_| unexpected()),
+                _ => Err(unexpected()),
+            },
+            Command::Log => Ok(Response::Log(
+                text.lines()
+                    .map(|line| line.trim_end_matches('\r').to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect(),
+            )),
+            Command::Set(..)
+            | Command::SetMode(_)
+            | Command::SetChecksum(_)
+            | Command::Reset
+            | Command::Commit => match text {
+                ACK_REPLY => Ok(Response::Ack),
+                _ => Err(unexpected()),
+            },
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::Hello => write!(f, "{}", DEFAULT_HANDSHAKE_REQUEST),
+            Command::Info => write!(f, "{}", DEVICE_INFO_REQUEST),
+            Command::Get(parameter) => write!(f, "get {}", parameter),
+            Command::Set(parameter, value) => write!(f, "set {} {}", parameter, value),
+            Command::SetMode(mode) => write!(f, "set {} {}", MODE_PARAMETER, mode),
+            Command::SetChecksum(checksum) => write!(f, "set {} {}", CHECKSUM_PARAMETER, checksum),
+            Command::Reset => write!(f, "reset"),
+            Command::Commit => write!(f, "commit"),
+            Command::Log => write!(f, "log"),
+        }
+    }
+}
+
+/// Ошибки разбора команды на стороне устройства
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandParseError {
+    /// Неизвестная команда или неверное число аргументов
+    UnknownCommand,
+    /// Значение не является числом
+    BadValue,
+}
+
+impl FromStr for Command {
+    type Err = CommandParseError;
+
+    fn from_str(request: &str) -> Result<Self, Self::Err> {
+        if request.trim() == DEVICE_INFO_REQUEST {
+            return Ok(Command::Info);
+        }
+
+        let tokens = request.split_whitespace().collect::<Vec<&str>>();
+
+        match tokens.as_slice() {
+            [DEFAULT_HANDSHAKE_REQUEST] => Ok(Command::Hello),
+            ["reset"] => Ok(Command::Reset),
+            ["commit"] => Ok(Command::Commit),
+            ["log"] => Ok(Command::Log),
+            ["get", parameter] => Ok(Command::Get(parameter.to_string())),
+            ["set", MODE_PARAMETER, mode] => mode
+                .parse()
+                .map(Command::SetMode)
+                .map_err(|_| CommandParseError::BadValue),
+            ["set", CHECKSUM_PARAMETER, checksum] => checksum
+                .parse()
+                .map(Command::SetChecksum)
+                .map_err(|_| CommandParseError::BadValue),
+            ["set", parameter, value] => value
+                .parse()
+                .map(|value| Command::Set(parameter.to_string(), value))
+                .map_err(|_| CommandParseError::BadValue),
+            _ => Err(CommandParseError::UnknownCommand),
+        }
+    }
+}
+
+impl Display for Response {
+    /// Текст ответа в том виде, в котором его отправляет устройство
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Response::Greeting(text) => write!(f, "{}\r\n", text),
+            Response::Info(info) => write!(f, "{}", info.to_reply()),
+            Response::Value { parameter, value } => write!(f, "{}:{}\r\n", parameter, value),
+            Response::Ack => write!(f, "{}\r\n", ACK_REPLY),
+            Response::Error(code) => write!(f, "{} {}\r\n", ERROR_PREFIX, code),
+            Response::Log(lines) => lines.iter().try_for_each(|line| write!(f, "{}\r\n", line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_command_round_trip() {
+        let commands = [
+            Command::Hello,
+            Command::Info,
+            Command::Get("groupnumber".to_string()),
+            Command::Set("musicvolume".to_string(), 3),
+            Command::SetMode(2),
+            Command::SetChecksum(Checksum::Crc16),
+            Command::Reset,
+            Command::Commit,
+            Command::Log,
+        ];
+
+        for command in commands {
+            assert_eq!(command.to_string().parse(), Ok(command));
+        }
+
+        assert_eq!(
+            "set groupnumber x".parse::<Command>(),
+            Err(CommandParseError::BadValue)
+        );
+        assert_eq!(
+            "reboot now".parse::<Command>(),
+            Err(CommandParseError::UnknownCommand)
+        );
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        let get = Command::Get("soundvolume".to_string());
+        let value = Response::Value {
+            parameter: "soundvolume".to_string(),
+            value: 2,
+        };
+
+        assert_eq!(get.parse_response(&value.to_string()).unwrap(), value);
+        assert_eq!(get.parse_response("ERR 2\r\n").unwrap(), Response::Error(2));
+        assert_eq!(
+            Command::SetMode(1).parse_response("OK\r\n").unwrap(),
+            Response::Ack
+        );
+
+        let log = Response::Log(vec!["#1 hello".to_string(), "#2 commit".to_string()]);
+        assert_eq!(Command::Log.parse_response(&log.to_string()).unwrap(), log);
+    }
+
+    #[test]
+    fn test_unexpected_response() {
+        let get = Command::Get("soundvolume".to_string());
+
+        assert!(matches!(
+            get.parse_response("musicvolume:2\r\n"),
+            Err(ClientError::UnexpectedReply { .. })
+        ));
+        assert!(matches!(
+            get.parse_response("soundvolume 2\r\n"),
+            Err(ClientError::UnexpectedReply { .. })
+        ));
+        assert!(matches!(
+            Command::Reset.parse_response("ERR x\r\n"),
+            Err(ClientError::UnexpectedReply { .. })
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_arbitrary_text(text in ".{0,64}") {
+            let _ = text.parse::<Command>();
+            let _ = text.parse::<DeviceInfo>();
+            let _ = Command::Get("groupnumber".to_string()).parse_response(&text);
+            let _ = Command::Log.parse_response(&text);
+        }
+    }
+}
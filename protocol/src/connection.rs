@@ -0,0 +1,167 @@
+use log::{debug, info};
+
+use crate::client::Link;
+use crate::fragment::Message;
+use crate::options::{HeartbeatMode, HeartbeatPolicy};
+use crate::reader::{Shared, lock};
+use std::fmt::Display;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Состояние соединения с устройством
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Установка соединения (приветствие)
+    Connecting,
+    /// Устройство на связи
+    Connected,
+    /// Пропущены проверки связи, но лимит пропусков не исчерпан
+    Degraded,
+    /// Связь потеряна (канал закрыт или лимит пропусков исчерпан)
+    Lost,
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Degraded => write!(f, "degraded"),
+            ConnectionState::Lost => write!(f, "lost"),
+        }
+    }
+}
+
+/// Текущее состояние соединения и уведомление подписчиков о его смене
+pub(crate) struct ConnectionMonitor {
+    state: Mutex<ConnectionState>,
+    watchers: Mutex<Vec<Sender<ConnectionState>>>,
+}
+
+impl ConnectionMonitor {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(ConnectionState::Connecting),
+            watchers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn get(&self) -> ConnectionState {
+        *lock(&self.state)
+    }
+
+    /// Смена состояния, подписчики уведомляются только об изменениях
+    ///
+    /// Из `Lost` соединение выходит только через `Connecting` (переподключение),
+    /// поэтому потерю связи, обнаруженную одним потоком, не затирает другой
+    pub(crate) fn set(&self, state: ConnectionState) {
+        let mut current = lock(&self.state);
+        let previous = *current;
+        if previous == state
+            || (previous == ConnectionState::Lost && state != ConnectionState::Connecting)
+        {
+            return;
+        }
+        *current = state;
+        drop(current);
+
+        info!("Connection {} -> {}", previous, state);
+        lock(&self.watchers).retain(|watcher| watcher.send(state).is_ok());
+    }
+
+    /// Подписка на смену состояния соединения
+    pub(crate) fn subscribe(&self) -> Receiver<ConnectionState> {
+        let (sender, receiver) = mpsc::channel();
+        lock(&self.watchers).push(sender);
+        receiver
+    }
+}
+
+/// Фоновый поток контроля связи
+///
+/// Каждые `HeartbeatPolicy::interval` проверяет, принимались ли пакеты
+/// от устройства. В режиме `Ping` при отсутствии пакетов отправляет запрос
+/// приветствия, если канал не занят запросом клиента
+pub(crate) struct Heartbeat {
+    /// Остановка потока (закрытие канала)
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    pub(crate) fn spawn(
+        policy: HeartbeatPolicy,
+        ping: Message,
+        response: Duration,
+        link: Arc<Mutex<Link>>,
+        shared: Arc<Shared>,
+        monitor: Arc<ConnectionMonitor>,
+    ) -> std::io::Result<Self> {
+        let (stop, stopped) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("mu-heartbeat".to_string())
+            .spawn(move || {
+                let mut missed: u8 = 0;
+
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(policy.interval) {
+                    // Канал закрыт: состояние выставил поток чтения
+                    if monitor.get() == ConnectionState::Lost {
+                        return;
+                    }
+
+                    let alive = if shared.idle_time() < policy.interval {
+                        Some(true)
+                    } else {
+                        match policy.mode {
+                            HeartbeatMode::Traffic => Some(false),
+                            HeartbeatMode::Ping => match link.try_lock() {
+                                Ok(mut link) => {
+                                    Some(link.exchange(&shared, &ping, response).is_ok())
+                                }
+                                // Идет запрос клиента: его результат и покажет состояние связи
+                                Err(TryLockError::WouldBlock) => None,
+                                Err(TryLockError::Poisoned(link)) => Some(
+                                    link.into_inner().exchange(&shared, &ping, response).is_ok(),
+                                ),
+                            },
+                        }
+                    };
+
+                    match alive {
+                        Some(true) => {
+                            missed = 0;
+                            monitor.set(ConnectionState::Connected);
+                        }
+                        Some(false) => {
+                            missed = missed.saturating_add(1);
+                            debug!("Heartbeat missed ({} of {})", missed, policy.missed_limit);
+
+                            if missed >= policy.missed_limit.max(1) {
+                                monitor.set(ConnectionState::Lost);
+                                return;
+                            }
+                            monitor.set(ConnectionState::Degraded);
+                        }
+                        None => (),
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
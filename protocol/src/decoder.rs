@@ -0,0 +1,271 @@
+use log::debug;
+use std::time::{Duration, Instant};
+
+use crate::checksum::Checksum;
+use crate::mu_frame::{MUFrame, SYNC1, frame_size};
+
+/// Потоковый декодер пакетов протокола "МЮ"
+///
+/// Накапливает байты между чтениями, ищет начало пакета (SYNC1),
+/// проверяет длину, CRC и SYNC2. Поврежденные байты отбрасываются
+/// до следующего SYNC1 (ресинхронизация).
+///
+/// ## Пример
+/// ```ignore
+/// let mut decoder = FrameDecoder::new();
+/// decoder.push(&raw_bytes);
+/// while let Some(frame) = decoder.next_frame() {
+///     println!("{}", frame);
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    /// Момент последнего добавления байтов
+    last_push: Option<Instant>,
+    /// Алгоритм контрольной суммы пакетов
+    checksum: Checksum,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::with_checksum(Checksum::default())
+    }
+
+    /// Декодер пакетов с заданным алгоритмом контрольной суммы
+    pub fn with_checksum(checksum: Checksum) -> Self {
+        Self {
+            buffer: Vec::new(),
+            last_push: None,
+            checksum,
+        }
+    }
+
+    pub fn get_checksum(&self) -> Checksum {
+        self.checksum
+    }
+
+    /// Смена алгоритма контрольной суммы
+    ///
+    /// Применяется к пакетам, разбор которых еще не выполнен
+    pub fn set_checksum(&mut self, checksum: Checksum) {
+        self.checksum = checksum;
+    }
+
+    /// Добавление принятых байтов в буфер декодера
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+        self.last_push = Some(Instant::now());
+    }
+
+    /// Время с момента приема последних байтов недопринятого пакета
+    ///
+    /// `None`, если буфер пуст
+    pub fn idle_time(&self) -> Option<Duration> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            self.last_push.map(|instant| instant.elapsed())
+        }
+    }
+
+    /// Количество байтов, ожидающих разбора
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Сброс накопленных данных
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Извлечение очередного полного пакета из буфера
+    ///
+    /// Возвращает `None`, если для пакета недостаточно данных
+    pub fn next_frame(&mut self) -> Option<MUFrame> {
+        loop {
+            // Поиск начала пакета
+            match self.buffer.iter().position(|&byte| byte == SYNC1) {
+                Some(0) => (),
+                Some(start) => {
+                    debug!("Dropping {} bytes before SYNC1", start);
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    if !self.buffer.is_empty() {
+                        debug!("Dropping {} bytes without SYNC1", self.buffer.len());
+                    }
+                    self.buffer.clear();
+                    return None;
+                }
+            }
+
+            // Префикс + длина
+            if self.buffer.len() < 2 {
+                return None;
+            }
+
+            let frame_size = frame_size(self.buffer[1], self.checksum);
+            if self.buffer.len() < frame_size {
+                return None;
+            }
+
+            match MUFrame::deserialize_with(&self.buffer[..frame_size], self.checksum) {
+                Ok(frame) => {
+                    self.buffer.drain(..frame_size);
+                    return Some(frame);
+                }
+                Err(e) => {
+                    // Ложный SYNC1 или поврежденный пакет: пропуск одного байта
+                    debug!("Corrupted frame ({}), resynchronizing", e);
+                    self.buffer.drain(..1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    fn console_frame(data: &[u8]) -> MUFrame {
+        let mut frame = MUFrame::new();
+        frame.set_data(data.to_vec()).unwrap();
+        frame
+    }
+
+    #[test]
+    fn test_split_reads() {
+        let frame = console_frame(b"get groupnumber\n");
+        let bytes = frame.serialize();
+
+        let mut decoder = FrameDecoder::new();
+        for byte in &bytes[..bytes.len() - 1] {
+            decoder.push(&[*byte]);
+            assert_eq!(decoder.next_frame(), None);
+        }
+        decoder.push(&bytes[bytes.len() - 1..]);
+
+        assert_eq!(decoder.next_frame(), Some(frame));
+        assert_eq!(decoder.buffered_len(), 0);
+    }
+
+    #[test]
+    fn test_leading_garbage() {
+        let frame = console_frame(b"Hi!\r\n");
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&[0x00, 0x13, 0xFF, 0x42]);
+        decoder.push(&frame.serialize());
+
+        assert_eq!(decoder.next_frame(), Some(frame));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_two_frames_in_one_read() {
+        let first = console_frame(b"groupnumber:3\r\n");
+        let second = console_frame(b"musicvolume:1\r\n");
+
+        let mut bytes = first.serialize();
+        bytes.extend(second.serialize());
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&bytes);
+
+        assert_eq!(decoder.next_frame(), Some(first));
+        assert_eq!(decoder.next_frame(), Some(second));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_resync_after_corrupted_frame() {
+        let good = console_frame(b"soundvolume:2\r\n");
+
+        let mut corrupted = console_frame(b"loadcapacity:7\r\n").serialize();
+        let crc_index = corrupted.len() - 2;
+        corrupted[crc_index] ^= 0xFF;
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&corrupted);
+        decoder.push(&good.serialize());
+
+        assert_eq!(decoder.next_frame(), Some(good));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_crc16_frames() {
+        let mut frame = console_frame(b"groupnumber:3\r\n");
+        frame.set_checksum(Checksum::Crc16);
+
+        // CRC-8 декодер не принимает пакет с более длинной контрольной суммой
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&frame.serialize());
+        assert_eq!(decoder.next_frame(), None);
+
+        let mut decoder = FrameDecoder::with_checksum(Checksum::Crc16);
+        decoder.push(&frame.serialize());
+        assert_eq!(decoder.next_frame(), Some(frame));
+        assert_eq!(decoder.buffered_len(), 0);
+    }
+
+    #[test]
+    fn test_false_sync_in_garbage() {
+        let frame = console_frame(b"Hi!\r\n");
+
+        let mut bytes = vec![SYNC1, 0x01, 0x00];
+        bytes.extend(frame.serialize());
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&bytes);
+
+        assert_eq!(decoder.next_frame(), Some(frame));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_frames_between_noise(
+            payloads in vec(vec(0..0x80u8, 1..64), 1..8),
+            noise in vec(vec(any::<u8>().prop_filter("no SYNC1", |b| *b != SYNC1), 0..16), 8),
+            chunk in 1..32usize,
+        ) {
+            let frames = payloads
+                .iter()
+                .map(|data| console_frame(data))
+                .collect::<Vec<MUFrame>>();
+
+            let mut bytes = Vec::new();
+            for (frame, noise) in frames.iter().zip(&noise) {
+                bytes.extend(noise);
+                bytes.extend(frame.serialize());
+            }
+
+            let mut decoder = FrameDecoder::new();
+            let mut decoded = Vec::new();
+            for part in bytes.chunks(chunk) {
+                decoder.push(part);
+                while let Some(frame) = decoder.next_frame() {
+                    decoded.push(frame);
+                }
+            }
+
+            prop_assert_eq!(decoded, frames);
+        }
+
+        #[test]
+        fn prop_line_noise(bytes in vec(any::<u8>(), 0..1024), chunk in 1..64usize) {
+            let mut decoder = FrameDecoder::new();
+            for part in bytes.chunks(chunk) {
+                decoder.push(part);
+                while decoder.next_frame().is_some() {}
+
+                // Недопринятый пакет не превышает максимального размера
+                prop_assert!(decoder.buffered_len() < frame_size(u8::MAX, Checksum::Crc8));
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::checksum::Checksum;
+
+/// Запрос сведений об устройстве
+pub const DEVICE_INFO_REQUEST: &str = "get server_info";
+
+/// Возможность устройства: запись параметров фиксируется командой `commit`
+pub const COMMIT_CAPABILITY: &str = "commit";
+
+/// Возможность устройства: ответ повторяет номер запроса (пакеты `Sequenced`)
+pub const SEQUENCE_CAPABILITY: &str = "seq";
+
+/// Возможность устройства: смена режима подтверждается ответом `OK`.
+/// Старые прошивки отвечают произвольным текстом (например, повтором режима)
+pub const ACK_CAPABILITY: &str = "ack";
+
+/// Версия протокола, реализованная клиентом.
+/// Устройства с другой старшей версией считаются несовместимыми
+pub const PROTOCOL_VERSION: Version = Version::new(1, 0, 0);
+
+/// Версия в формате `major.minor[.patch]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl Version {
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Совместимость версий протокола (совпадение старшей версии)
+    pub fn is_compatible_with(&self, other: &Version) -> bool {
+        self.major == other.major
+    }
+}
+
+impl FromStr for Version {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.trim().trim_start_matches('v').split('.');
+
+        let mut next = |required: bool| match parts.next() {
+            Some(part) => part.parse::<u16>().map_err(|_| ()),
+            None if required => Err(()),
+            None => Ok(0),
+        };
+
+        let version = Version::new(next(true)?, next(false)?, next(false)?);
+
+        match parts.next() {
+            Some(_) => Err(()),
+            None => Ok(version),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Сведения об устройстве, полученные при подключении
+///
+/// Устройство отвечает на `get server_info` строкой вида
+/// `model=MU-2;fw=1.4.0;proto=1.0;sn=000123;params=groupnumber,musicvolume;caps=commit;crc=crc8,crc16`.
+/// Поля `sn`, `params`, `caps` и `crc` необязательны, неизвестные поля
+/// и алгоритмы контрольной суммы игнорируются
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub model: String,
+    pub firmware_version: Version,
+    pub protocol_version: Version,
+    pub serial_number: Option<String>,
+    /// Поддерживаемые параметры (пустой список - устройство их не сообщило)
+    pub parameters: Vec<String>,
+    /// Дополнительные возможности прошивки (например, `commit`)
+    pub capabilities: Vec<String>,
+    /// Алгоритмы контрольной суммы, на которые устройство может переключиться
+    pub checksums: Vec<Checksum>,
+}
+
+impl DeviceInfo {
+    /// Поддержка параметра устройством
+    ///
+    /// Если список параметров не сообщен, считается, что поддерживаются все
+    pub fn supports_parameter(&self, parameter: &str) -> bool {
+        self.parameters.is_empty() || self.parameters.iter().any(|p| p == parameter)
+    }
+
+    /// Наличие возможности у прошивки
+    pub fn supports_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Поддержка алгоритма контрольной суммы
+    ///
+    /// Устройства, не сообщившие список, поддерживают только CRC-8
+    pub fn supports_checksum(&self, checksum: Checksum) -> bool {
+        checksum == Checksum::default() || self.checksums.contains(&checksum)
+    }
+
+    /// Совместимость протокола устройства с клиентом
+    pub fn is_compatible(&self) -> bool {
+        self.protocol_version.is_compatible_with(&PROTOCOL_VERSION)
+    }
+
+    /// Строка ответа на `get server_info`
+    pub fn to_reply(&self) -> String {
+        let mut reply = format!(
+            "model={};fw={};proto={}",
+            self.model, self.firmware_version, self.protocol_version
+        );
+        if let Some(serial_number) = &self.serial_number {
+            reply.push_str(&format!(";sn={}", serial_number));
+        }
+        if !self.parameters.is_empty() {
+            reply.push_str(&format!(";params={}", self.parameters.join(",")));
+        }
+        if !self.capabilities.is_empty() {
+            reply.push_str(&format!(";caps={}", self.capabilities.join(",")));
+        }
+        if !self.checksums.is_empty() {
+            let checksums = self
+                .checksums
+                .iter()
+                .map(Checksum::to_string)
+                .collect::<Vec<String>>();
+            reply.push_str(&format!(";crc={}", checksums.join(",")));
+        }
+        reply.push_str("\r\n");
+        reply
+    }
+}
+
+impl FromStr for DeviceInfo {
+    type Err = ();
+
+    fn from_str(reply: &str) -> Result<Self, Self::Err> {
+        let mut model = None;
+        let mut firmware_version = None;
+        let mut protocol_version = None;
+        let mut serial_number = None;
+        let mut parameters = Vec::new();
+        let mut capabilities = Vec::new();
+        let mut checksums = Vec::new();
+
+        for field in reply.trim_end_matches(['\r', '\n']).split(';') {
+            let (key, value) = field.split_once('=').ok_or(())?;
+            let value = value.trim();
+
+            match key.trim() {
+                "model" => model = Some(value.to_string()),
+                "fw" => firmware_version = Some(value.parse()?),
+                "proto" => protocol_version = Some(value.parse()?),
+                "sn" => serial_number = Some(value.to_string()),
+                "params" => parameters = split_list(value),
+                "caps" => capabilities = split_list(value),
+                "crc" => {
+                    checksums = split_list(value)
+                        .iter()
+                        .filter_map(|name| name.parse().ok())
+                        .collect()
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            model: model.filter(|m| !m.is_empty()).ok_or(())?,
+            firmware_version: firmware_version.ok_or(())?,
+            protocol_version: protocol_version.ok_or(())?,
+            serial_number,
+            parameters,
+            capabilities,
+            checksums,
+        })
+    }
+}
+
+/// Разбор списка через запятую
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (firmware {}, protocol {}, S/N {})",
+            self.model,
+            self.firmware_version,
+            self.protocol_version,
+            self.serial_number.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_parse() {
+        assert_eq!("1.4.2".parse(), Ok(Version::new(1, 4, 2)));
+        assert_eq!("v2.1".parse(), Ok(Version::new(2, 1, 0)));
+        assert_eq!("3".parse(), Ok(Version::new(3, 0, 0)));
+        assert_eq!("1.x".parse::<Version>(), Err(()));
+        assert_eq!("1.2.3.4".parse::<Version>(), Err(()));
+        assert!(Version::new(1, 9, 0).is_compatible_with(&PROTOCOL_VERSION));
+        assert!(!Version::new(2, 0, 0).is_compatible_with(&PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn test_device_info_round_trip() {
+        let reply = "model=MU-2;fw=1.4.0;proto=1.0;sn=000123;params=groupnumber,musicvolume;caps=commit;crc=crc8,crc16\r\n";
+        let info: DeviceInfo = reply.parse().unwrap();
+
+        assert_eq!(info.model, "MU-2");
+        assert_eq!(info.firmware_version, Version::new(1, 4, 0));
+        assert_eq!(info.serial_number.as_deref(), Some("000123"));
+        assert!(info.supports_parameter("musicvolume"));
+        assert!(!info.supports_parameter("loadcapacity"));
+        assert!(info.supports_capability(COMMIT_CAPABILITY));
+        assert!(info.supports_checksum(Checksum::Crc16));
+        assert!(!info.supports_checksum(Checksum::Xor));
+        assert!(info.is_compatible());
+
+        assert_eq!(info.to_reply().parse::<DeviceInfo>(), Ok(info));
+    }
+
+    #[test]
+    fn test_device_info_errors() {
+        assert!("ERR 1\r\n".parse::<DeviceInfo>().is_err());
+        assert!("model=MU-2;fw=1.0\r\n".parse::<DeviceInfo>().is_err());
+
+        let info: DeviceInfo = "model=MU-1;fw=0.9;proto=1;extra=1".parse().unwrap();
+        assert_eq!(info.serial_number, None);
+        assert!(info.supports_parameter("loadcapacity"));
+        assert!(!info.supports_capability(COMMIT_CAPABILITY));
+        assert!(info.supports_checksum(Checksum::Crc8));
+        assert!(!info.supports_checksum(Checksum::Crc16));
+
+        let info: DeviceInfo = "model=MU-3;fw=2.0;proto=1.1;crc=crc32,xor".parse().unwrap();
+        assert_eq!(info.checksums, vec![Checksum::Xor]);
+    }
+}
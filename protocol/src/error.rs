@@ -0,0 +1,80 @@
+use std::string::FromUtf8Error;
+
+use thiserror::Error;
+
+use crate::device_info::Version;
+
+/// Ошибки формирования и разбора пакета
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum FrameError {
+    #[error("Data is empty")]
+    EmptyData,
+    #[error("Data too long: {0} bytes")]
+    DataTooLong(usize),
+    #[error("Bad encoding")]
+    BadEncoding,
+    #[error("Unknown opcode: {0:#04X}")]
+    UnknownOpcode(u8),
+    #[error("Bad frame length: {expected} bytes expected, got {actual}")]
+    BadLength { expected: usize, actual: usize },
+    #[error("Payload too short: {needed} bytes needed, {remaining} remaining")]
+    PayloadTooShort { needed: usize, remaining: usize },
+    #[error("Bad prefix: {0:#04X}")]
+    BadPrefix(u8),
+    #[error("Bad postfix: {0:#04X}")]
+    BadPostfix(u8),
+    #[error("Bad CRC: expected {expected:#04X}, got {actual:#04X}")]
+    BadCrc { expected: u16, actual: u16 },
+    #[error("Bad {field} value: {value}")]
+    BadField { field: &'static str, value: u8 },
+    #[error("Fragment out of sequence: expected {expected}, got {actual}")]
+    FragmentSequence { expected: u8, actual: u8 },
+}
+
+/// Ошибки обмена с устройством
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Unable to open {port}: {source}")]
+    PortOpen {
+        port: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Timeout has been reached")]
+    Timeout,
+    #[error("Connection closed")]
+    ConnectionClosed,
+    #[error("Reconnect is not available for this transport")]
+    ReconnectUnavailable,
+    #[error("Handshake failed after {attempts} attempts")]
+    HandshakeFailed { attempts: u8 },
+    #[error("Unexpected reply to \"{request}\": {reply:?}")]
+    UnexpectedReply { request: String, reply: String },
+    #[error("Device rejected \"{request}\" with code {code}")]
+    Rejected { request: String, code: u8 },
+    #[error("Incompatible protocol version {device} (supported {host})")]
+    IncompatibleProtocol { device: Version, host: Version },
+    #[error("Frame error: {0}")]
+    Frame(#[from] FrameError),
+    #[error("Response is not valid UTF-8: {0}")]
+    Encoding(#[from] FromUtf8Error),
+    #[error("I/O error: {0}")]
+    Io(std::io::Error),
+}
+
+impl ClientError {
+    /// Ошибка может быть устранена повторной отправкой запроса
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ClientError::Timeout | ClientError::Frame(_))
+    }
+}
+
+impl From<std::io::Error> for ClientError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => ClientError::Timeout,
+            std::io::ErrorKind::UnexpectedEof => ClientError::ConnectionClosed,
+            _ => ClientError::Io(error),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt::Display;
+
+use crate::error::FrameError;
+use crate::payload::{PayloadReader, PayloadWriter};
+
+/// Запрос состояния в режиме OnDemand (полезная нагрузка пакета `Streaming`)
+pub const STATUS_REQUEST: u8 = 0x01;
+
+/// Флаги состояния индикатора
+const FLAG_ARRIVAL_GONG: u8 = 0x01;
+const FLAG_OVERLOAD: u8 = 0x02;
+const FLAG_FIRE_MODE: u8 = 0x04;
+const FLAG_OUT_OF_SERVICE: u8 = 0x08;
+
+/// Направление движения кабины
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    #[default]
+    Idle = 0,
+    Up = 1,
+    Down = 2,
+}
+
+/// Состояние дверей кабины
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DoorState {
+    #[default]
+    Closed = 0,
+    Opening = 1,
+    Open = 2,
+    Closing = 3,
+}
+
+impl TryFrom<u8> for Direction {
+    type Error = FrameError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Direction::Idle),
+            1 => Ok(Direction::Up),
+            2 => Ok(Direction::Down),
+            _ => Err(FrameError::BadField {
+                field: "direction",
+                value,
+            }),
+        }
+    }
+}
+
+impl TryFrom<u8> for DoorState {
+    type Error = FrameError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(DoorState::Closed),
+            1 => Ok(DoorState::Opening),
+            2 => Ok(DoorState::Open),
+            3 => Ok(DoorState::Closing),
+            _ => Err(FrameError::BadField {
+                field: "door state",
+                value,
+            }),
+        }
+    }
+}
+
+/// Состояние лифта, передаваемое индикатором в пакетах `Streaming`
+///
+/// Формат полезной нагрузки (little-endian):
+/// `floor: i8, direction: u8, door: u8, flags: u8, fault_count: u8, faults: [u16]`.
+/// Байты после списка неисправностей игнорируются (расширения прошивки)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceEvent {
+    /// Этаж (отрицательные значения - подземные этажи)
+    pub floor: i8,
+    pub direction: Direction,
+    pub door: DoorState,
+    /// Сигнал прибытия кабины (гонг)
+    pub arrival_gong: bool,
+    /// Перегрузка кабины
+    pub overload: bool,
+    /// Режим пожарной опасности
+    pub fire_mode: bool,
+    /// Лифт выведен из обслуживания
+    pub out_of_service: bool,
+    /// Коды активных неисправностей
+    pub faults: Vec<u16>,
+}
+
+impl DeviceEvent {
+    /// Разбор полезной нагрузки пакета потоковых данных
+    pub fn from_payload(data: &[u8]) -> Result<Self, FrameError> {
+        let mut reader = PayloadReader::new(data);
+
+        let floor = reader.get_i8()?;
+        let direction = Direction::try_from(reader.get_u8()?)?;
+        let door = DoorState::try_from(reader.get_u8()?)?;
+        let flags = reader.get_u8()?;
+
+        let fault_count = reader.get_u8()?;
+        let faults = (0..fault_count)
+            .map(|_| reader.get_u16())
+            .collect::<Result<Vec<u16>, FrameError>>()?;
+
+        Ok(Self {
+            floor,
+            direction,
+            door,
+            arrival_gong: flags & FLAG_ARRIVAL_GONG != 0,
+            overload: flags & FLAG_OVERLOAD != 0,
+            fire_mode: flags & FLAG_FIRE_MODE != 0,
+            out_of_service: flags & FLAG_OUT_OF_SERVICE != 0,
+            faults,
+        })
+    }
+
+    /// Полезная нагрузка пакета потоковых данных
+    ///
+    /// Передается не более 255 кодов неисправностей
+    pub fn to_payload(&self) -> Vec<u8> {
+        let faults = &self.faults[..self.faults.len().min(u8::MAX as usize)];
+
+        let mut writer = PayloadWriter::new()
+            .put_i8(self.floor)
+            .put_u8(self.direction as u8)
+            .put_u8(self.door as u8)
+            .put_u8(self.flags())
+            .put_u8(faults.len() as u8);
+        for fault in faults {
+            writer = writer.put_u16(*fault);
+        }
+
+        writer.into_bytes()
+    }
+
+    fn flags(&self) -> u8 {
+        [
+            (self.arrival_gong, FLAG_ARRIVAL_GONG),
+            (self.overload, FLAG_OVERLOAD),
+            (self.fire_mode, FLAG_FIRE_MODE),
+            (self.out_of_service, FLAG_OUT_OF_SERVICE),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::Idle => write!(f, "idle"),
+            Direction::Up => write!(f, "up"),
+            Direction::Down => write!(f, "down"),
+        }
+    }
+}
+
+impl Display for DoorState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DoorState::Closed => write!(f, "closed"),
+            DoorState::Opening => write!(f, "opening"),
+            DoorState::Open => write!(f, "open"),
+            DoorState::Closing => write!(f, "closing"),
+        }
+    }
+}
+
+impl Display for DeviceEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Floor {}, {}, door {}",
+            self.floor, self.direction, self.door
+        )?;
+
+        for (set, name) in [
+            (self.arrival_gong, "gong"),
+            (self.overload, "overload"),
+            (self.fire_mode, "fire mode"),
+            (self.out_of_service, "out of service"),
+        ] {
+            if set {
+                write!(f, ", {}", name)?;
+            }
+        }
+
+        if !self.faults.is_empty() {
+            let faults = self
+                .faults
+                .iter()
+                .map(|code| format!("{:#06X}", code))
+                .collect::<Vec<String>>();
+            write!(f, ", faults: {}", faults.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_event_round_trip() {
+        let event = DeviceEvent {
+            floor: -2,
+            direction: Direction::Up,
+            door: DoorState::Closing,
+            arrival_gong: true,
+            fire_mode: true,
+            faults: vec![0x0012, 0x0304],
+            ..Default::default()
+        };
+
+        let payload = event.to_payload();
+        assert_eq!(
+            payload,
+            vec![0xFE, 0x01, 0x03, 0x05, 0x02, 0x12, 0x00, 0x04, 0x03]
+        );
+        assert_eq!(DeviceEvent::from_payload(&payload), Ok(event.clone()));
+        assert_eq!(
+            event.to_string(),
+            "Floor -2, up, door closing, gong, fire mode, faults: 0x0012 0x0304"
+        );
+    }
+
+    #[test]
+    fn test_event_errors() {
+        assert_eq!(
+            DeviceEvent::from_payload(&[0x01, 0x03, 0x00, 0x00, 0x00]),
+            Err(FrameError::BadField {
+                field: "direction",
+                value: 3
+            })
+        );
+        assert!(matches!(
+            DeviceEvent::from_payload(&[0x01, 0x00, 0x00, 0x00, 0x02, 0x12, 0x00]),
+            Err(FrameError::PayloadTooShort { .. })
+        ));
+
+        // Байты расширений прошивки пропускаются
+        let event = DeviceEvent::from_payload(&[0x05, 0x00, 0x02, 0x00, 0x00, 0xAA]).unwrap();
+        assert_eq!(event.floor, 5);
+        assert_eq!(event.door, DoorState::Open);
+    }
+
+    fn arb_event() -> impl Strategy<Value = DeviceEvent> {
+        (
+            any::<i8>(),
+            0..3u8,
+            0..4u8,
+            any::<[bool; 4]>(),
+            vec(any::<u16>(), 0..=255usize),
+        )
+            .prop_map(|(floor, direction, door, flags, faults)| DeviceEvent {
+                floor,
+                direction: Direction::try_from(direction).unwrap(),
+                door: DoorState::try_from(door).unwrap(),
+                arrival_gong: flags[0],
+                overload: flags[1],
+                fire_mode: flags[2],
+                out_of_service: flags[3],
+                faults,
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_event_round_trip(event in arb_event()) {
+            prop_assert_eq!(DeviceEvent::from_payload(&event.to_payload()), Ok(event));
+        }
+
+        #[test]
+        fn prop_arbitrary_payload(bytes in vec(any::<u8>(), 0..64)) {
+            let _ = DeviceEvent::from_payload(&bytes);
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use log::warn;
+
+use crate::checksum::Checksum;
+use crate::error::FrameError;
+use crate::mu_frame::{MAX_DATA_SIZE, MUFrame};
+use crate::opcode::Opcode;
+use crate::payload::{PayloadReader, PayloadWriter};
+
+/// Заголовок фрагмента: опкод сообщения, номер фрагмента, флаги
+const FRAGMENT_HEADER_SIZE: usize = 3;
+/// Данные сообщения в одном фрагменте
+const FRAGMENT_DATA_SIZE: usize = MAX_DATA_SIZE as usize - FRAGMENT_HEADER_SIZE;
+/// Флаг фрагмента: за ним следует продолжение
+const FLAG_CONTINUED: u8 = 0x01;
+/// Максимальная длина сообщения (номер фрагмента - один байт)
+pub const MAX_MESSAGE_SIZE: usize = FRAGMENT_DATA_SIZE * (u8::MAX as usize + 1);
+
+/// Сообщение протокола "МЮ" произвольной длины
+///
+/// Сообщение, не помещающееся в один пакет, передается последовательностью
+/// пакетов `Fragment`. Полезная нагрузка фрагмента начинается с заголовка
+/// `opcode: u8, sequence: u8, flags: u8` (бит 0 - будет продолжение),
+/// номера фрагментов идут подряд с нуля.
+///
+/// Сообщение с номером запроса передается пакетом `Sequenced`, полезная
+/// нагрузка которого начинается с заголовка `opcode: u8, id: u8`.
+/// Устройство повторяет номер запроса в ответе
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    opcode: Opcode,
+    data: Vec<u8>,
+    id: Option<u8>,
+}
+
+impl Message {
+    /// Сообщение с проверкой длины и кодировки данных
+    pub fn new(opcode: Opcode, data: Vec<u8>) -> Result<Self, FrameError> {
+        if data.is_empty() {
+            return Err(FrameError::EmptyData);
+        }
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(FrameError::DataTooLong(data.len()));
+        }
+        if matches!(opcode, Opcode::Fragment | Opcode::Sequenced) {
+            return Err(FrameError::UnknownOpcode(opcode.into()));
+        }
+        if opcode.is_text() && !data.is_ascii() {
+            return Err(FrameError::BadEncoding);
+        }
+
+        Ok(Self {
+            opcode,
+            data,
+            id: None,
+        })
+    }
+
+    /// Сообщение из принятых данных с разбором заголовка `Sequenced`
+    fn from_parts(opcode: Opcode, data: Vec<u8>) -> Result<Self, FrameError> {
+        if opcode != Opcode::Sequenced {
+            return Self::new(opcode, data);
+        }
+
+        let mut reader = PayloadReader::new(&data);
+        let opcode = Opcode::try_from(reader.get_u8()?)?;
+        let id = reader.get_u8()?;
+
+        Ok(Self::new(opcode, reader.rest().to_vec())?.with_id(id))
+    }
+
+    /// Сообщение с номером запроса
+    pub fn with_id(mut self, id: u8) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn get_opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    pub fn get_data(&self) -> &Vec<u8> {
+        &self.data
+    }
+
+    /// Номер запроса (`None` - сообщение без номера)
+    pub fn get_id(&self) -> Option<u8> {
+        self.id
+    }
+
+    /// Разбиение на пакеты для отправки
+    ///
+    /// Сообщение, помещающееся в один пакет, передается без фрагментации
+    pub fn to_frames(&self, checksum: Checksum) -> Result<Vec<MUFrame>, FrameError> {
+        let (opcode, data) = match self.id {
+            Some(id) => (
+                Opcode::Sequenced,
+                PayloadWriter::new()
+                    .put_u8(self.opcode.into())
+                    .put_u8(id)
+                    .put_bytes(&self.data)
+                    .into_bytes(),
+            ),
+            None => (self.opcode, self.data.clone()),
+        };
+
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(FrameError::DataTooLong(data.len()));
+        }
+
+        if data.len() <= MAX_DATA_SIZE as usize {
+            let mut frame = MUFrame::with_opcode(opcode);
+            frame.set_data(data)?;
+            frame.set_checksum(checksum);
+            return Ok(vec![frame]);
+        }
+
+        let chunks = data.chunks(FRAGMENT_DATA_SIZE);
+        let last = chunks.len() - 1;
+
+        chunks
+            .enumerate()
+            .map(|(sequence, chunk)| {
+                let flags = if sequence < last { FLAG_CONTINUED } else { 0 };
+
+                let mut frame = MUFrame::with_opcode(Opcode::Fragment);
+                frame.set_data(
+                    PayloadWriter::new()
+                        .put_u8(opcode.into())
+                        .put_u8(sequence as u8)
+                        .put_u8(flags)
+                        .put_bytes(chunk)
+                        .into_bytes(),
+                )?;
+                frame.set_checksum(checksum);
+                Ok(frame)
+            })
+            .collect()
+    }
+}
+
+/// Сообщение из одного пакета без разбора заголовков
+impl From<MUFrame> for Message {
+    fn from(frame: MUFrame) -> Self {
+        Self {
+            opcode: frame.get_opcode(),
+            data: frame.get_data().clone(),
+            id: None,
+        }
+    }
+}
+
+/// Сборка сообщений из принятых пакетов
+///
+/// Пакеты других классов между фрагментами одного сообщения допускаются
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    /// Собираемое сообщение: опкод, номер следующего фрагмента, данные
+    pending: Option<(Opcode, u8, Vec<u8>)>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self { pending: None }
+    }
+
+    /// Прием пакета, возвращает сообщение, если оно собрано полностью
+    ///
+    /// При нарушении порядка фрагментов собираемое сообщение отбрасывается
+    pub fn push(&mut self, frame: MUFrame) -> Result<Option<Message>, FrameError> {
+        if frame.get_opcode() != Opcode::Fragment {
+            return Message::from_parts(frame.get_opcode(), frame.get_data().clone()).map(Some);
+        }
+
+        let mut reader = PayloadReader::new(frame.get_data());
+        let opcode = Opcode::try_from(reader.get_u8()?)?;
+        let sequence = reader.get_u8()?;
+        let flags = reader.get_u8()?;
+        let chunk = reader.rest();
+
+        if sequence == 0 {
+            if let Some((opcode, next, _)) = self.pending.take() {
+                warn!(
+                    "Incomplete message dropped: {} after {} fragments",
+                    opcode, next
+                );
+            }
+            self.pending = Some((opcode, 0, Vec::new()));
+        }
+
+        let Some((pending_opcode, next, data)) = self.pending.as_mut() else {
+            return Err(FrameError::FragmentSequence {
+                expected: 0,
+                actual: sequence,
+            });
+        };
+
+        if *pending_opcode != opcode || *next != sequence {
+            let expected = *next;
+            self.pending = None;
+            return Err(FrameError::FragmentSequence {
+                expected,
+                actual: sequence,
+            });
+        }
+
+        data.extend_from_slice(chunk);
+        *next = next.wrapping_add(1);
+
+        if flags & FLAG_CONTINUED != 0 {
+            return Ok(None);
+        }
+
+        let data = std::mem::take(data);
+        self.pending = None;
+        Message::from_parts(opcode, data).map(Some)
+    }
+
+    /// Сброс собираемого сообщения
+    pub fn clear(&mut self) {
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    fn reassemble(frames: Vec<MUFrame>) -> Vec<Result<Option<Message>, FrameError>> {
+        let mut reassembler = Reassembler::new();
+        frames
+            .into_iter()
+            .map(|frame| reassembler.push(frame))
+            .collect()
+    }
+
+    #[test]
+    fn test_short_message() {
+        let message = Message::new(Opcode::Console, b"OK\r\n".to_vec()).unwrap();
+        let frames = message.to_frames(Checksum::Crc8).unwrap();
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].get_opcode(), Opcode::Console);
+        assert_eq!(reassemble(frames), vec![Ok(Some(message))]);
+    }
+
+    #[test]
+    fn test_fragmented_message() {
+        let text = (0..600).map(|i| b'a' + (i % 26) as u8).collect::<Vec<u8>>();
+        let message = Message::new(Opcode::Console, text).unwrap();
+        let frames = message.to_frames(Checksum::Crc8).unwrap();
+
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|f| f.get_opcode() == Opcode::Fragment));
+        assert_eq!(frames[2].get_data()[..3], [Opcode::CONSOLE, 2, 0]);
+
+        let results = reassemble(frames);
+        assert_eq!(results[..2], [Ok(None), Ok(None)]);
+        assert_eq!(results[2], Ok(Some(message)));
+    }
+
+    #[test]
+    fn test_interleaved_frames() {
+        let message = Message::new(Opcode::Parameter, vec![0x55; 300]).unwrap();
+        let mut frames = message.to_frames(Checksum::Crc8).unwrap();
+
+        let mut stream = MUFrame::with_opcode(Opcode::Streaming);
+        stream.set_data(vec![0x01]).unwrap();
+        frames.insert(1, stream.clone());
+
+        let results = reassemble(frames);
+        assert_eq!(results[1], Ok(Some(Message::from(stream))));
+        assert_eq!(results[2], Ok(Some(message)));
+    }
+
+    #[test]
+    fn test_lost_fragment() {
+        let message = Message::new(Opcode::Console, vec![b'x'; 800]).unwrap();
+        let mut frames = message.to_frames(Checksum::Crc8).unwrap();
+        frames.remove(1);
+
+        let results = reassemble(frames);
+        assert_eq!(
+            results[1],
+            Err(FrameError::FragmentSequence {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert!(results[2].is_err());
+    }
+
+    #[test]
+    fn test_message_limits() {
+        assert_eq!(
+            Message::new(Opcode::Console, vec![b'x'; MAX_MESSAGE_SIZE + 1]),
+            Err(FrameError::DataTooLong(MAX_MESSAGE_SIZE + 1))
+        );
+
+        let message = Message::new(Opcode::Console, vec![b'x'; MAX_MESSAGE_SIZE]).unwrap();
+        assert_eq!(message.to_frames(Checksum::Crc8).unwrap().len(), 256);
+        // Заголовок номера запроса не помещается в последний фрагмент
+        assert_eq!(
+            message.with_id(1).to_frames(Checksum::Crc8),
+            Err(FrameError::DataTooLong(MAX_MESSAGE_SIZE + 2))
+        );
+    }
+
+    #[test]
+    fn test_sequenced_message() {
+        let message = Message::new(Opcode::Console, b"get groupnumber\n".to_vec())
+            .unwrap()
+            .with_id(0x2A);
+        let frames = message.to_frames(Checksum::Crc8).unwrap();
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].get_opcode(), Opcode::Sequenced);
+        assert_eq!(frames[0].get_data()[..3], [Opcode::CONSOLE, 0x2A, b'g']);
+        assert_eq!(reassemble(frames), vec![Ok(Some(message))]);
+
+        let long = Message::new(Opcode::Console, vec![b'l'; 700])
+            .unwrap()
+            .with_id(7);
+        let results = reassemble(long.to_frames(Checksum::Crc8).unwrap());
+        assert_eq!(results.last(), Some(&Ok(Some(long))));
+
+        let mut nested = MUFrame::with_opcode(Opcode::Sequenced);
+        nested.set_data(vec![Opcode::SEQUENCED, 1, 0x00]).unwrap();
+        assert_eq!(
+            reassemble(vec![nested]),
+            vec![Err(FrameError::UnknownOpcode(Opcode::SEQUENCED))]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_message_round_trip(data in vec(any::<u8>(), 1..2000)) {
+            let message = Message::new(Opcode::Parameter, data).unwrap();
+
+            let mut reassembler = Reassembler::new();
+            let mut received = None;
+            for frame in message.to_frames(Checksum::Crc16).unwrap() {
+                prop_assert!(received.is_none());
+                received = reassembler.push(frame).unwrap();
+            }
+
+            prop_assert_eq!(received, Some(message));
+        }
+
+        #[test]
+        fn prop_arbitrary_fragments(payloads in vec(vec(any::<u8>(), 1..=255usize), 0..16)) {
+            let mut reassembler = Reassembler::new();
+            for payload in payloads {
+                let mut frame = MUFrame::with_opcode(Opcode::Fragment);
+                frame.set_data(payload).unwrap();
+                let _ = reassembler.push(frame);
+            }
+        }
+    }
+}
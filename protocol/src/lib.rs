@@ -1,59 +1,128 @@
+pub mod checksum;
 pub mod client;
+pub mod command;
+pub mod connection;
+pub mod decoder;
+pub mod device_info;
+pub mod error;
+pub mod event;
+pub mod fragment;
 pub mod mu_frame;
-
+pub mod opcode;
+pub mod options;
+pub mod payload;
+mod reader;
+pub mod transport;
+
+use crate::decoder::FrameDecoder;
+use crate::error::ClientError;
 use crate::mu_frame::MUFrame;
+use crate::transport::Transport;
+
+use std::io::{ErrorKind, Write};
+use std::time::{Duration, Instant};
 
-use std::{
-    io::{Read, Write},
-    thread,
-};
+use log::debug;
 
-/// Задержка приема ответа
-const ANSWER_DELAY_MS: u64 = 500;
+/// Минимальный таймаут чтения (нулевой таймаут некоторые порты не принимают)
+const MIN_READ_TIMEOUT: Duration = Duration::from_millis(1);
 
 /// Отправка сообщения
-fn send_proto_message<Writer: Write>(data: MUFrame, mut writer: Writer) -> Result<(), String> {
+fn send_proto_message<Writer: Write + ?Sized>(
+    data: MUFrame,
+    writer: &mut Writer,
+) -> Result<(), ClientError> {
     let bytes = data.serialize();
-    writer.write_all(&bytes).map_err(|e| e.to_string())?;
-
-    // Время для составления ответа
-    thread::sleep(std::time::Duration::from_millis(ANSWER_DELAY_MS));
+    writer.write_all(&bytes)?;
+    writer.flush()?;
 
     Ok(())
 }
 
 /// Прием сообщения
-fn recv_proto_message<Reader: Read>(mut reader: Reader) -> Result<MUFrame, String> {
-    let mut raw_frame = Vec::new();
+///
+/// Читает данные до тех пор, пока декодер не выделит полный пакет
+/// или не наступит крайний срок `deadline`. Пакет, прием которого
+/// прервался дольше чем на `inter_byte`, отбрасывается. Недопринятый
+/// к крайнему сроку пакет остается в декодере до следующего вызова
+fn recv_proto_message<T: Transport + ?Sized>(
+    transport: &mut T,
+    decoder: &mut FrameDecoder,
+    deadline: Instant,
+    inter_byte: Duration,
+) -> Result<MUFrame, ClientError> {
     let mut read_buffer = [0; 256];
 
-    // Чтение отклика от интерфейсной платы
-    reader.read(&mut read_buffer).map_err(|e| e.to_string())?;
-
-    let prefix = read_buffer[0];
-    raw_frame.push(prefix);
-
-    let payload_length = read_buffer[1] as u8;
-    raw_frame.push(payload_length);
-
-    let opcode = read_buffer[2];
-    raw_frame.push(opcode);
-
-    let payload = &read_buffer[3..3 + payload_length as usize];
-    raw_frame.extend_from_slice(payload);
-
-    let crc = read_buffer[3 + payload_length as usize];
-    raw_frame.push(crc);
-
-    let postfix = read_buffer[4 + payload_length as usize];
-    raw_frame.push(postfix);
-
-    Ok(MUFrame::deserialize(&raw_frame).map_err(|e| e.to_string())?)
+    loop {
+        // Пакет мог остаться в буфере после предыдущего чтения
+        if let Some(frame) = decoder.next_frame() {
+            return Ok(frame);
+        }
+
+        // Прием пакета прервался: остаток уже не придет
+        let idle = decoder.idle_time();
+        if idle.is_some_and(|idle| idle >= inter_byte) {
+            debug!(
+                "Inter-byte timeout, dropping {} bytes",
+                decoder.buffered_len()
+            );
+            decoder.clear();
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(ClientError::Timeout);
+        }
+
+        let mut wait = deadline - now;
+        if let Some(idle) = decoder.idle_time() {
+            wait = wait.min(inter_byte.saturating_sub(idle));
+        }
+        transport.set_timeout(wait.max(MIN_READ_TIMEOUT))?;
+
+        // Чтение отклика от интерфейсной платы
+        match transport.read(&mut read_buffer) {
+            Ok(0) => return Err(ClientError::ConnectionClosed),
+            Ok(size) => match read_buffer.get(..size) {
+                Some(bytes) => decoder.push(bytes),
+                // Транспорт сообщил о большем числе байтов, чем помещается в буфер
+                None => {
+                    return Err(ClientError::Io(std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "read returned {} bytes for a {} byte buffer",
+                            size,
+                            read_buffer.len()
+                        ),
+                    )));
+                }
+            },
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transport::MemoryPipe;
+    use std::thread;
+
+    /// Прием с крайним сроком `response_ms` от текущего момента
+    fn recv<T: Transport + ?Sized>(
+        transport: &mut T,
+        decoder: &mut FrameDecoder,
+        response_ms: u64,
+        inter_byte_ms: u64,
+    ) -> Result<MUFrame, ClientError> {
+        recv_proto_message(
+            transport,
+            decoder,
+            Instant::now() + Duration::from_millis(response_ms),
+            Duration::from_millis(inter_byte_ms),
+        )
+    }
 
     #[test]
     fn test_send_and_recv() {
@@ -62,12 +131,75 @@ mod tests {
             .set_data(b"get server_info\n".to_vec())
             .unwrap();
 
-        let mut buf = Vec::new();
+        let (mut host, mut device) = MemoryPipe::pair(Duration::from_millis(100));
 
-        send_proto_message(frame_to_send.clone(), &mut buf).unwrap();
+        send_proto_message(frame_to_send.clone(), &mut host).unwrap();
 
-        let received_frame = recv_proto_message(&buf[..]).unwrap();
+        let mut decoder = FrameDecoder::new();
+        let received_frame = recv(&mut device, &mut decoder, 100, 50).unwrap();
         assert_eq!(received_frame.get_data(), frame_to_send.get_data());
         assert_eq!(received_frame, frame_to_send);
     }
+
+    #[test]
+    fn test_recv_from_chunked_stream() {
+        let mut first = MUFrame::new();
+        first.set_data(b"groupnumber:3\r\n".to_vec()).unwrap();
+        let mut second = MUFrame::new();
+        second.set_data(b"musicvolume:2\r\n".to_vec()).unwrap();
+
+        let mut stream = vec![0x00, 0x55];
+        stream.extend(first.serialize());
+        stream.extend(second.serialize());
+
+        let (mut host, mut device) = MemoryPipe::pair(Duration::from_millis(100));
+
+        // Запись по 3 байта за раз
+        for chunk in stream.chunks(3) {
+            device.write_all(chunk).unwrap();
+        }
+        drop(device);
+
+        let mut decoder = FrameDecoder::new();
+
+        assert_eq!(recv(&mut host, &mut decoder, 100, 50).unwrap(), first);
+        assert_eq!(recv(&mut host, &mut decoder, 100, 50).unwrap(), second);
+        assert!(matches!(
+            recv(&mut host, &mut decoder, 100, 50),
+            Err(ClientError::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn test_response_deadline() {
+        let (mut host, _device) = MemoryPipe::pair(Duration::from_secs(10));
+
+        let started = Instant::now();
+        let result = recv(&mut host, &mut FrameDecoder::new(), 80, 20);
+
+        assert!(matches!(result, Err(ClientError::Timeout)));
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_inter_byte_timeout_drops_partial_frame() {
+        // Начало пакета с заявленной длиной 200 байт, остаток которого не придет
+        let stalled = vec![0xAA, 200, 0xC0, b'g'];
+        let mut complete = MUFrame::new();
+        complete.set_data(b"groupnumber:4\r\n".to_vec()).unwrap();
+
+        let (mut host, mut device) = MemoryPipe::pair(Duration::from_millis(100));
+
+        let writer = thread::spawn(move || {
+            device.write_all(&stalled).unwrap();
+            thread::sleep(Duration::from_millis(100));
+            device.write_all(&complete.serialize()).unwrap();
+            device
+        });
+
+        let received = recv(&mut host, &mut FrameDecoder::new(), 1000, 30).unwrap();
+        assert_eq!(received.get_data(), b"groupnumber:4\r\n");
+
+        writer.join().unwrap();
+    }
 }
@@ -1,9 +1,20 @@
 use std::fmt::Display;
 
-const SYNC1: u8 = 0xAA;
+use crate::checksum::Checksum;
+use crate::error::FrameError;
+use crate::opcode::Opcode;
+use crate::payload::PayloadReader;
+
+pub(crate) const SYNC1: u8 = 0xAA;
 const SYNC2: u8 = 0xBB;
-const MAX_DATA_SIZE: u8 = u8::MAX;
-const CONSOLE_OPCODE: u8 = 0xC0;
+/// Служебные байты пакета без контрольной суммы: префикс, длина, опкод, постфикс
+const FRAME_HEADER_SIZE: usize = 4;
+pub(crate) const MAX_DATA_SIZE: u8 = u8::MAX;
+
+/// Полный размер пакета с данными длины `length`
+pub(crate) fn frame_size(length: u8, checksum: Checksum) -> usize {
+    FRAME_HEADER_SIZE + checksum.width() + length as usize
+}
 
 /// Пакет данных протокола "МЮ" и методы работы с ним
 ///
@@ -21,36 +32,75 @@ const CONSOLE_OPCODE: u8 = 0xC0;
 pub struct MUFrame {
     prefix: u8,
     length: u8,
-    opcode: u8,
+    opcode: Opcode,
     data: Vec<u8>,
-    crc: u8,
+    checksum: Checksum,
+    crc: u16,
     suffix: u8,
 }
 
+impl Default for MUFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MUFrame {
+    /// Пакет текстовой консоли
     pub fn new() -> Self {
+        Self::with_opcode(Opcode::Console)
+    }
+
+    /// Пакет с заданным классом сообщения
+    pub fn with_opcode(opcode: Opcode) -> Self {
         Self {
             prefix: SYNC1,
             length: 0,
-            opcode: CONSOLE_OPCODE,
+            opcode,
             data: Vec::with_capacity(MAX_DATA_SIZE as usize),
+            checksum: Checksum::default(),
             crc: 0,
             suffix: SYNC2,
         }
     }
 
+    pub fn get_opcode(&self) -> Opcode {
+        self.opcode
+    }
+
     pub fn get_data(&self) -> &Vec<u8> {
         &self.data
     }
 
+    pub fn get_checksum(&self) -> Checksum {
+        self.checksum
+    }
+
+    /// Выбор алгоритма контрольной суммы с ее пересчетом
+    pub fn set_checksum(&mut self, checksum: Checksum) {
+        self.checksum = checksum;
+        self.crc = self.calculate_src();
+    }
+
+    /// Чтение бинарных полей полезной нагрузки
+    pub fn payload_reader(&self) -> PayloadReader<'_> {
+        PayloadReader::new(&self.data)
+    }
+
     /// Загрузка данных в фрейм, вычисление CRC и длины
-    pub fn set_data(&mut self, data: Vec<u8>) -> Result<(), String> {
-        if data.len() > MAX_DATA_SIZE as usize || data.is_empty() {
-            return Err("Data too long".to_string());
+    ///
+    /// Для текстовой консоли допускаются только ASCII символы
+    pub fn set_data(&mut self, data: Vec<u8>) -> Result<(), FrameError> {
+        if data.is_empty() {
+            return Err(FrameError::EmptyData);
         }
 
-        if !data.is_ascii() {
-            return Err("Bad encoding".to_string());
+        if data.len() > MAX_DATA_SIZE as usize {
+            return Err(FrameError::DataTooLong(data.len()));
+        }
+
+        if self.opcode.is_text() && !data.is_ascii() {
+            return Err(FrameError::BadEncoding);
         }
 
         self.length = data.len() as u8;
@@ -60,16 +110,44 @@ impl MUFrame {
         Ok(())
     }
 
-    /// Десериализация данных из буфера
-    pub fn deserialize(data: &[u8]) -> Result<Self, String> {
-        let mut frame = Self::new();
-        frame.prefix = data[0];
-        frame.length = data[1];
-        frame.opcode = data[2];
+    /// Десериализация данных из буфера (контрольная сумма CRC-8)
+    ///
+    /// Буфер должен содержать ровно один пакет. Усеченный или лишний
+    /// ввод возвращается как `FrameError::BadLength`
+    pub fn deserialize(data: &[u8]) -> Result<Self, FrameError> {
+        Self::deserialize_with(data, Checksum::default())
+    }
+
+    /// Десериализация пакета с заданным алгоритмом контрольной суммы
+    pub fn deserialize_with(data: &[u8], checksum: Checksum) -> Result<Self, FrameError> {
+        let (&prefix, &length) = match data {
+            [prefix, length, ..] => (prefix, length),
+            _ => {
+                return Err(FrameError::BadLength {
+                    expected: frame_size(0, checksum),
+                    actual: data.len(),
+                });
+            }
+        };
+
+        let frame_size = frame_size(length, checksum);
+        if data.len() != frame_size {
+            return Err(FrameError::BadLength {
+                expected: frame_size,
+                actual: data.len(),
+            });
+        }
 
-        frame.data = data[3..3 + frame.length as usize].to_vec();
-        frame.crc = data[3 + frame.length as usize];
-        frame.suffix = data[4 + frame.length as usize];
+        // Длина проверена: опкод, данные, CRC и постфикс присутствуют
+        let mut reader = PayloadReader::new(&data[2..]);
+        let mut frame = Self::new();
+        frame.prefix = prefix;
+        frame.length = length;
+        frame.opcode = Opcode::try_from(reader.get_u8()?)?;
+        frame.data = reader.get_bytes(length as usize)?.to_vec();
+        frame.checksum = checksum;
+        frame.crc = checksum.read(&mut reader)?;
+        frame.suffix = reader.get_u8()?;
 
         frame.invalidate_frame()?;
 
@@ -78,48 +156,49 @@ impl MUFrame {
 
     /// Сериализация данных
     pub fn serialize(&self) -> Vec<u8> {
-        let mut result = Vec::with_capacity(5 + self.length as usize);
+        let mut result = Vec::with_capacity(frame_size(self.length, self.checksum));
         result.push(self.prefix);
         result.push(self.length);
-        result.push(self.opcode);
+        result.push(self.opcode.into());
         result.extend(self.data.iter());
-        result.push(self.crc);
+        self.checksum.write(self.crc, &mut result);
         result.push(self.suffix);
         result
     }
 
     /// Проверка валидности фрейма
-    fn invalidate_frame(&self) -> Result<(), String> {
+    fn invalidate_frame(&self) -> Result<(), FrameError> {
         if !self.is_prefix_correct() {
-            return Err("Bad prefix".to_string());
+            return Err(FrameError::BadPrefix(self.prefix));
         }
         if !self.is_postfix_correct() {
-            return Err("Bad postfix".to_string());
+            return Err(FrameError::BadPostfix(self.suffix));
         }
         if !self.is_crc_valid(self.crc) {
-            return Err("Bad CRC".to_string());
+            return Err(FrameError::BadCrc {
+                expected: self.calculate_src(),
+                actual: self.crc,
+            });
         }
 
-        if !self.data.is_ascii() {
-            return Err("Bad encoding".to_string());
+        if self.opcode.is_text() && !self.data.is_ascii() {
+            return Err(FrameError::BadEncoding);
         }
 
         Ok(())
     }
 
-    /// Вычисление CRC
-    fn calculate_src(&self) -> u8 {
-        const CRC8_MU: crc::Crc<u8> = crc::Crc::<u8>::new(&crc::CRC_8_NRSC_5);
-
+    /// Вычисление контрольной суммы по опкоду и данным
+    fn calculate_src(&self) -> u16 {
         let mut crc_data = Vec::with_capacity(self.length as usize + 1);
-        crc_data.push(self.opcode);
+        crc_data.push(self.opcode.into());
         crc_data.extend(self.data.iter());
 
-        CRC8_MU.checksum(&crc_data)
+        self.checksum.compute(&crc_data)
     }
 
     /// Проверка соответствия фактического CRC посылки с принятым
-    fn is_crc_valid(&self, crc: u8) -> bool {
+    fn is_crc_valid(&self, crc: u16) -> bool {
         let calculated_crc = self.calculate_src();
         calculated_crc == crc
     }
@@ -139,15 +218,19 @@ impl Display for MUFrame {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "MU Message: \n 1.Opcode={}, \n 2.Data length={} \n 3.Payload={:?} \n 4.CRC={}",
-            self.opcode, self.length, self.data, self.crc
+            "MU Message: \n 1.Opcode={}, \n 2.Data length={} \n 3.Payload={:?} \n 4.CRC={} ({})",
+            self.opcode, self.length, self.data, self.crc, self.checksum
         )
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
+    use crate::payload::PayloadWriter;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
 
     #[test]
     fn test_check_crc_calculation() {
@@ -184,7 +267,7 @@ mod tests {
         assert_eq!(frame.is_prefix_correct(), true);
         assert_eq!(frame.is_postfix_correct(), true);
         assert_eq!(frame.is_crc_valid(0x1E), true);
-        assert_eq!(frame.opcode, CONSOLE_OPCODE);
+        assert_eq!(frame.opcode, Opcode::Console);
         assert_eq!(frame.data, b"Test string!\n");
     }
 
@@ -198,8 +281,206 @@ mod tests {
         assert_eq!(frame.is_prefix_correct(), true);
         assert_eq!(frame.is_postfix_correct(), true);
         assert_eq!(frame.is_crc_valid(0x1E), true);
-        assert_eq!(frame.opcode, CONSOLE_OPCODE);
+        assert_eq!(frame.opcode, Opcode::Console);
         assert_eq!(frame.data, b"Test string!\n");
         frame.invalidate_frame().unwrap();
     }
+
+    #[test]
+    fn test_opcode_round_trip() {
+        let mut frame = MUFrame::with_opcode(Opcode::vendor(0xE1).unwrap());
+        frame.set_data(b"vendor\n".to_vec()).unwrap();
+
+        let serialized_vec = frame.serialize();
+        assert_eq!(serialized_vec[2], 0xE1);
+
+        let deserialized = MUFrame::deserialize(&serialized_vec).unwrap();
+        assert_eq!(deserialized.get_opcode(), Opcode::vendor(0xE1).unwrap());
+        assert_eq!(deserialized, frame);
+
+        let mut unknown = serialized_vec.clone();
+        unknown[2] = 0x42;
+        assert_eq!(
+            MUFrame::deserialize(&unknown),
+            Err(FrameError::UnknownOpcode(0x42))
+        );
+    }
+
+    #[test]
+    fn test_binary_payload() {
+        let payload = PayloadWriter::new()
+            .put_u8(0xFF)
+            .put_u16(0xAABB)
+            .put_u32(1000)
+            .into_bytes();
+
+        let mut frame = MUFrame::with_opcode(Opcode::Parameter);
+        frame.set_data(payload.clone()).unwrap();
+
+        let deserialized = MUFrame::deserialize(&frame.serialize()).unwrap();
+        assert_eq!(deserialized.get_data(), &payload);
+
+        let mut reader = deserialized.payload_reader();
+        assert_eq!(reader.get_u8(), Ok(0xFF));
+        assert_eq!(reader.get_u16(), Ok(0xAABB));
+        assert_eq!(reader.get_u32(), Ok(1000));
+
+        let mut console = MUFrame::new();
+        assert_eq!(console.set_data(payload), Err(FrameError::BadEncoding));
+    }
+
+    #[test]
+    fn test_frame_errors() {
+        let mut frame = MUFrame::new();
+        assert_eq!(frame.set_data(vec![]), Err(FrameError::EmptyData));
+        assert_eq!(
+            frame.set_data(vec![b'a'; 256]),
+            Err(FrameError::DataTooLong(256))
+        );
+        assert_eq!(frame.set_data(vec![0xFF]), Err(FrameError::BadEncoding));
+
+        let mut serialized_vec = vec![
+            0xAA, 0x0D, 0xC0, 0x54, 0x65, 0x73, 0x74, 0x20, 0x73, 0x74, 0x72, 0x69, 0x6E, 0x67,
+            0x21, 0x0A, 0x1F, 0xBB,
+        ];
+        assert_eq!(
+            MUFrame::deserialize(&serialized_vec),
+            Err(FrameError::BadCrc {
+                expected: 0x1E,
+                actual: 0x1F
+            })
+        );
+
+        serialized_vec[0] = 0xAB;
+        assert_eq!(
+            MUFrame::deserialize(&serialized_vec),
+            Err(FrameError::BadPrefix(0xAB))
+        );
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        let bytes = {
+            let mut frame = MUFrame::new();
+            frame.set_data(b"Hi!\r\n".to_vec()).unwrap();
+            frame.serialize()
+        };
+
+        for size in 0..bytes.len() {
+            assert!(matches!(
+                MUFrame::deserialize(&bytes[..size]),
+                Err(FrameError::BadLength { .. })
+            ));
+        }
+
+        let mut extended = bytes.clone();
+        extended.push(0x00);
+        assert_eq!(
+            MUFrame::deserialize(&extended),
+            Err(FrameError::BadLength {
+                expected: bytes.len(),
+                actual: bytes.len() + 1
+            })
+        );
+        assert_eq!(
+            MUFrame::deserialize(&[SYNC1, 0xFF, 0xC0]),
+            Err(FrameError::BadLength {
+                expected: 260,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_wide_checksum() {
+        let mut frame = MUFrame::with_opcode(Opcode::Parameter);
+        frame.set_data(vec![0x01, 0x02]).unwrap();
+        frame.set_checksum(Checksum::Crc16);
+
+        let serialized_vec = frame.serialize();
+        assert_eq!(serialized_vec.len(), 8);
+        assert_eq!(serialized_vec[..5], [0xAA, 0x02, 0xC1, 0x01, 0x02]);
+        assert_eq!(serialized_vec[7], 0xBB);
+
+        assert_eq!(
+            MUFrame::deserialize_with(&serialized_vec, Checksum::Crc16),
+            Ok(frame.clone())
+        );
+        // Пакет с другим алгоритмом не принимается
+        assert!(MUFrame::deserialize(&serialized_vec).is_err());
+
+        frame.set_checksum(Checksum::Xor);
+        assert_eq!(
+            frame.serialize(),
+            vec![0xAA, 0x02, 0xC1, 0x01, 0x02, 0xC2, 0xBB]
+        );
+
+        let mut corrupted = frame.serialize();
+        corrupted[5] = 0x00;
+        assert_eq!(
+            MUFrame::deserialize_with(&corrupted, Checksum::Xor),
+            Err(FrameError::BadCrc {
+                expected: 0xC2,
+                actual: 0x00
+            })
+        );
+    }
+
+    fn arb_opcode() -> impl Strategy<Value = Opcode> {
+        prop_oneof![
+            Just(Opcode::Console),
+            Just(Opcode::Parameter),
+            Just(Opcode::Streaming),
+            Just(Opcode::Fragment),
+            Just(Opcode::Bootloader),
+            (0xE0..=0xEFu8).prop_map(|code| Opcode::vendor(code).unwrap()),
+        ]
+    }
+
+    fn arb_checksum() -> impl Strategy<Value = Checksum> {
+        prop::sample::select(Checksum::ALL.to_vec())
+    }
+
+    /// Пакет с допустимыми для опкода данными
+    fn arb_frame() -> impl Strategy<Value = MUFrame> {
+        (arb_opcode(), arb_checksum()).prop_flat_map(|(opcode, checksum)| {
+            let byte = if opcode.is_text() {
+                0..=0x7Fu8
+            } else {
+                0..=u8::MAX
+            };
+            vec(byte, 1..=MAX_DATA_SIZE as usize).prop_map(move |data| {
+                let mut frame = MUFrame::with_opcode(opcode);
+                frame.set_data(data).unwrap();
+                frame.set_checksum(checksum);
+                frame
+            })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(frame in arb_frame()) {
+            let checksum = frame.get_checksum();
+            prop_assert_eq!(MUFrame::deserialize_with(&frame.serialize(), checksum), Ok(frame));
+        }
+
+        #[test]
+        fn prop_arbitrary_input(bytes in vec(any::<u8>(), 0..300), checksum in arb_checksum()) {
+            let _ = MUFrame::deserialize_with(&bytes, checksum);
+        }
+
+        #[test]
+        fn prop_corrupted_byte_detected(
+            frame in arb_frame(),
+            index in any::<prop::sample::Index>(),
+            flip in 1..=u8::MAX,
+        ) {
+            let mut bytes = frame.serialize();
+            let index = index.index(bytes.len());
+            bytes[index] ^= flip;
+
+            prop_assert!(MUFrame::deserialize_with(&bytes, frame.get_checksum()).is_err());
+        }
+    }
 }
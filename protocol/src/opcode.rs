@@ -0,0 +1,135 @@
+use std::fmt::Display;
+
+use crate::error::FrameError;
+
+/// Начало диапазона опкодов, зарезервированных под нужды производителя
+const VENDOR_OPCODE_FIRST: u8 = 0xE0;
+/// Конец диапазона опкодов производителя
+const VENDOR_OPCODE_LAST: u8 = 0xEF;
+
+/// Опкод производителя (0xE0..=0xEF)
+///
+/// Создается только с проверкой диапазона: `VendorOpcode::new` или `Opcode::try_from`
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct VendorOpcode(u8);
+
+impl VendorOpcode {
+    /// Создание опкода производителя с проверкой диапазона
+    pub fn new(code: u8) -> Result<Self, FrameError> {
+        if (VENDOR_OPCODE_FIRST..=VENDOR_OPCODE_LAST).contains(&code) {
+            Ok(VendorOpcode(code))
+        } else {
+            Err(FrameError::UnknownOpcode(code))
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Класс сообщения протокола "МЮ"
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Opcode {
+    /// Текстовая консоль (запрос/ответ в ASCII)
+    Console,
+    /// Бинарный доступ к параметрам устройства
+    Parameter,
+    /// Потоковые данные от устройства
+    Streaming,
+    /// Фрагмент сообщения, не помещающегося в один пакет
+    Fragment,
+    /// Сообщение с номером запроса (сопоставление ответов с запросами)
+    Sequenced,
+    /// Загрузчик (обновление прошивки)
+    Bootloader,
+    /// Сообщения производителя (0xE0..=0xEF)
+    Vendor(VendorOpcode),
+}
+
+impl Opcode {
+    pub const CONSOLE: u8 = 0xC0;
+    pub const PARAMETER: u8 = 0xC1;
+    pub const STREAMING: u8 = 0xC2;
+    pub const FRAGMENT: u8 = 0xC3;
+    pub const SEQUENCED: u8 = 0xC4;
+    pub const BOOTLOADER: u8 = 0xB0;
+
+    /// Признак текстового (ASCII) класса сообщений
+    ///
+    /// Остальные классы передают произвольные бинарные данные
+    pub fn is_text(&self) -> bool {
+        matches!(self, Opcode::Console)
+    }
+
+    /// Создание опкода производителя с проверкой диапазона
+    pub fn vendor(code: u8) -> Result<Self, FrameError> {
+        VendorOpcode::new(code).map(Opcode::Vendor)
+    }
+}
+
+impl From<Opcode> for u8 {
+    fn from(opcode: Opcode) -> Self {
+        match opcode {
+            Opcode::Console => Opcode::CONSOLE,
+            Opcode::Parameter => Opcode::PARAMETER,
+            Opcode::Streaming => Opcode::STREAMING,
+            Opcode::Fragment => Opcode::FRAGMENT,
+            Opcode::Sequenced => Opcode::SEQUENCED,
+            Opcode::Bootloader => Opcode::BOOTLOADER,
+            Opcode::Vendor(vendor) => vendor.code(),
+        }
+    }
+}
+
+impl TryFrom<u8> for Opcode {
+    type Error = FrameError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            Opcode::CONSOLE => Ok(Opcode::Console),
+            Opcode::PARAMETER => Ok(Opcode::Parameter),
+            Opcode::STREAMING => Ok(Opcode::Streaming),
+            Opcode::FRAGMENT => Ok(Opcode::Fragment),
+            Opcode::SEQUENCED => Ok(Opcode::Sequenced),
+            Opcode::BOOTLOADER => Ok(Opcode::Bootloader),
+            code => Opcode::vendor(code),
+        }
+    }
+}
+
+impl Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} ({:#04X})", self, u8::from(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_round_trip() {
+        for opcode in [
+            Opcode::Console,
+            Opcode::Parameter,
+            Opcode::Streaming,
+            Opcode::Fragment,
+            Opcode::Sequenced,
+            Opcode::Bootloader,
+            Opcode::vendor(0xE5).unwrap(),
+        ] {
+            assert_eq!(Opcode::try_from(u8::from(opcode)), Ok(opcode));
+        }
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        assert_eq!(Opcode::try_from(0x00), Err(FrameError::UnknownOpcode(0x00)));
+        assert_eq!(Opcode::vendor(0xF0), Err(FrameError::UnknownOpcode(0xF0)));
+        assert_eq!(
+            VendorOpcode::new(0x01),
+            Err(FrameError::UnknownOpcode(0x01))
+        );
+    }
+}
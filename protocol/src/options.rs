@@ -0,0 +1,317 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::checksum::Checksum;
+
+/// Время ожидания полного ответа по умолчанию
+pub const DEFAULT_RESPONSE_TIMEOUT_MS: u64 = 1000;
+/// Допустимая пауза между байтами одного ответа по умолчанию
+pub const DEFAULT_INTER_BYTE_TIMEOUT_MS: u64 = 100;
+
+/// Интервал контроля связи по умолчанию
+pub const DEFAULT_HEARTBEAT_INTERVAL_MS: u64 = 1000;
+
+/// Запрос приветствия по умолчанию
+pub const DEFAULT_HANDSHAKE_REQUEST: &str = "hello";
+/// Ожидаемый ответ на приветствие по умолчанию
+pub const DEFAULT_HANDSHAKE_REPLY: &str = "Hi!";
+
+/// Параметры ожидания ответа устройства
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseTimeouts {
+    /// Крайний срок получения полного ответа с момента отправки запроса
+    pub response: Duration,
+    /// Максимальная пауза между байтами начатого пакета.
+    /// По ее истечении недопринятый пакет отбрасывается
+    pub inter_byte: Duration,
+}
+
+impl Default for ResponseTimeouts {
+    fn default() -> Self {
+        Self {
+            response: Duration::from_millis(DEFAULT_RESPONSE_TIMEOUT_MS),
+            inter_byte: Duration::from_millis(DEFAULT_INTER_BYTE_TIMEOUT_MS),
+        }
+    }
+}
+
+/// Проверка текстового ответа устройства
+///
+/// Сравнение выполняется без завершающих символов конца строки (`\r\n`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplyMatcher {
+    /// Ответ совпадает с образцом
+    Exact(String),
+    /// Ответ начинается с образца
+    Prefix(String),
+    /// Ответ содержит образец
+    Contains(String),
+    /// Подходит любой из вариантов
+    AnyOf(Vec<ReplyMatcher>),
+}
+
+impl ReplyMatcher {
+    pub fn matches(&self, reply: &str) -> bool {
+        let reply = reply.trim_end_matches(['\r', '\n']);
+
+        match self {
+            ReplyMatcher::Exact(pattern) => reply == pattern,
+            ReplyMatcher::Prefix(pattern) => reply.starts_with(pattern.as_str()),
+            ReplyMatcher::Contains(pattern) => reply.contains(pattern.as_str()),
+            ReplyMatcher::AnyOf(matchers) => matchers.iter().any(|m| m.matches(reply)),
+        }
+    }
+}
+
+/// Разбор описания из конфига: `exact:Hi!`, `prefix:Hi`, `contains:MU`.
+/// Варианты разделяются `|`, описание без префикса считается точным совпадением
+impl From<&str> for ReplyMatcher {
+    fn from(value: &str) -> Self {
+        let mut matchers = value
+            .split('|')
+            .map(|variant| {
+                if let Some(pattern) = variant.strip_prefix("exact:") {
+                    ReplyMatcher::Exact(pattern.to_string())
+                } else if let Some(pattern) = variant.strip_prefix("prefix:") {
+                    ReplyMatcher::Prefix(pattern.to_string())
+                } else if let Some(pattern) = variant.strip_prefix("contains:") {
+                    ReplyMatcher::Contains(pattern.to_string())
+                } else {
+                    ReplyMatcher::Exact(variant.to_string())
+                }
+            })
+            .collect::<Vec<ReplyMatcher>>();
+
+        match matchers.len() {
+            1 => matchers.remove(0),
+            _ => ReplyMatcher::AnyOf(matchers),
+        }
+    }
+}
+
+/// Политика повторных попыток
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Максимальное количество попыток (не менее одной)
+    pub attempts: u8,
+    /// Пауза перед второй попыткой
+    pub backoff: Duration,
+    /// Множитель паузы для каждой следующей попытки
+    pub backoff_factor: u32,
+    /// Ограничение суммарного времени всех попыток
+    pub total_timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Единственная попытка без повторов
+    pub fn none() -> Self {
+        Self {
+            attempts: 1,
+            backoff: Duration::ZERO,
+            backoff_factor: 1,
+            total_timeout: None,
+        }
+    }
+
+    /// Пауза перед очередной попыткой
+    ///
+    /// `attempts_made` - количество уже выполненных попыток,
+    /// `elapsed` - время с начала первой попытки.
+    /// Возвращает `None`, если попытки исчерпаны
+    pub fn next_attempt(&self, attempts_made: u8, elapsed: Duration) -> Option<Duration> {
+        if attempts_made >= self.attempts.max(1) {
+            return None;
+        }
+
+        if attempts_made == 0 {
+            return Some(Duration::ZERO);
+        }
+
+        let factor = self
+            .backoff_factor
+            .saturating_pow(u32::from(attempts_made) - 1);
+        let delay = self.backoff.saturating_mul(factor);
+
+        match self.total_timeout {
+            Some(total) if elapsed.saturating_add(delay) >= total => None,
+            _ => Some(delay),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Политика установки соединения с устройством
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakePolicy {
+    /// Запрос приветствия
+    pub request: String,
+    /// Допустимые ответы на приветствие
+    pub reply: ReplyMatcher,
+    /// Повторы приветствия
+    pub retry: RetryPolicy,
+    /// Запрос сведений об устройстве после приветствия
+    /// (по умолчанию отключен: старые прошивки не отвечают на запрос)
+    pub query_info: bool,
+    /// Алгоритм контрольной суммы, на который следует перейти после
+    /// подключения, если устройство сообщило о его поддержке
+    pub checksum: Option<Checksum>,
+    /// Нумерация запросов, если устройство сообщило о ее поддержке
+    /// в сведениях об устройстве (требует `query_info`)
+    pub sequence_ids: bool,
+}
+
+impl Default for HandshakePolicy {
+    fn default() -> Self {
+        Self {
+            request: DEFAULT_HANDSHAKE_REQUEST.to_string(),
+            reply: ReplyMatcher::Exact(DEFAULT_HANDSHAKE_REPLY.to_string()),
+            retry: RetryPolicy {
+                attempts: 3,
+                backoff: Duration::from_millis(100),
+                backoff_factor: 2,
+                total_timeout: None,
+            },
+            query_info: false,
+            checksum: None,
+            sequence_ids: false,
+        }
+    }
+}
+
+/// Способ контроля связи с устройством
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatMode {
+    /// Запрос приветствия, если за интервал не было принято ни одного пакета
+    Ping,
+    /// Только наблюдение за входящими пакетами (устройство передает
+    /// потоковые данные периодически)
+    Traffic,
+}
+
+impl FromStr for HeartbeatMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ping" => Ok(HeartbeatMode::Ping),
+            "traffic" => Ok(HeartbeatMode::Traffic),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for HeartbeatMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeartbeatMode::Ping => write!(f, "ping"),
+            HeartbeatMode::Traffic => write!(f, "traffic"),
+        }
+    }
+}
+
+/// Политика контроля связи с устройством
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    pub mode: HeartbeatMode,
+    /// Интервал проверки связи
+    pub interval: Duration,
+    /// Число пропущенных проверок подряд, после которого связь считается потерянной
+    pub missed_limit: u8,
+    /// Переподключение и повторное приветствие при следующем запросе
+    /// после потери связи
+    pub reconnect: bool,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            mode: HeartbeatMode::Ping,
+            interval: Duration::from_millis(DEFAULT_HEARTBEAT_INTERVAL_MS),
+            missed_limit: 3,
+            reconnect: true,
+        }
+    }
+}
+
+/// Настройки клиента протокола
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientOptions {
+    pub timeouts: ResponseTimeouts,
+    pub handshake: HandshakePolicy,
+    /// Повторы запросов, оставшихся без ответа
+    pub retry: RetryPolicy,
+    /// Алгоритм контрольной суммы при подключении
+    pub checksum: Checksum,
+    /// Контроль связи (`None` - отключен)
+    pub heartbeat: Option<HeartbeatPolicy>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply_matcher() {
+        let matcher = ReplyMatcher::from("Hi!");
+        assert_eq!(matcher, ReplyMatcher::Exact("Hi!".to_string()));
+        assert!(matcher.matches("Hi!\r\n"));
+        assert!(!matcher.matches("Hi!!\r\n"));
+
+        let matcher = ReplyMatcher::from("prefix:Hi|contains:MU-2");
+        assert!(matcher.matches("Hi there\r\n"));
+        assert!(matcher.matches("Hello from MU-2\r\n"));
+        assert!(!matcher.matches("Hello\r\n"));
+    }
+
+    #[test]
+    fn test_handshake_defaults() {
+        // Старые прошивки не отвечают на запрос сведений и не нумеруют ответы
+        let policy = HandshakePolicy::default();
+        assert!(!policy.query_info);
+        assert!(!policy.sequence_ids);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy {
+            attempts: 4,
+            backoff: Duration::from_millis(10),
+            backoff_factor: 2,
+            total_timeout: None,
+        };
+
+        assert_eq!(policy.next_attempt(0, Duration::ZERO), Some(Duration::ZERO));
+        assert_eq!(
+            policy.next_attempt(1, Duration::ZERO),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(
+            policy.next_attempt(3, Duration::ZERO),
+            Some(Duration::from_millis(40))
+        );
+        assert_eq!(policy.next_attempt(4, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_retry_total_timeout() {
+        let policy = RetryPolicy {
+            attempts: 10,
+            backoff: Duration::from_millis(100),
+            backoff_factor: 1,
+            total_timeout: Some(Duration::from_millis(250)),
+        };
+
+        assert_eq!(
+            policy.next_attempt(1, Duration::from_millis(100)),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(policy.next_attempt(2, Duration::from_millis(200)), None);
+        assert_eq!(RetryPolicy::none().next_attempt(1, Duration::ZERO), None);
+    }
+}
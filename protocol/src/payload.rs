@@ -0,0 +1,155 @@
+use crate::error::FrameError;
+
+/// Формирование бинарной полезной нагрузки пакета
+///
+/// Целочисленные поля записываются в порядке little-endian
+///
+/// ## Пример
+/// ```ignore
+/// let mut frame = MUFrame::with_opcode(Opcode::Parameter);
+/// frame.set_data(PayloadWriter::new().put_u8(0x01).put_u16(1000).into_bytes())?;
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct PayloadWriter {
+    data: Vec<u8>,
+}
+
+/// Разбор бинарной полезной нагрузки пакета
+#[derive(Debug, Clone)]
+pub struct PayloadReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+macro_rules! le_fields {
+    ($(($put:ident, $get:ident, $type:ty)),* $(,)?) => {
+        impl PayloadWriter {
+            $(
+                pub fn $put(mut self, value: $type) -> Self {
+                    self.data.extend_from_slice(&value.to_le_bytes());
+                    self
+                }
+            )*
+        }
+
+        impl PayloadReader<'_> {
+            $(
+                pub fn $get(&mut self) -> Result<$type, FrameError> {
+                    let bytes = self.get_bytes(size_of::<$type>())?;
+                    // Длина среза проверена в get_bytes
+                    Ok(<$type>::from_le_bytes(bytes.try_into().unwrap_or_default()))
+                }
+            )*
+        }
+    };
+}
+
+le_fields!(
+    (put_u8, get_u8, u8),
+    (put_i8, get_i8, i8),
+    (put_u16, get_u16, u16),
+    (put_i16, get_i16, i16),
+    (put_u32, get_u32, u32),
+    (put_i32, get_i32, i32),
+);
+
+impl PayloadWriter {
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    /// Запись произвольных байтов без преобразования
+    pub fn put_bytes(mut self, bytes: &[u8]) -> Self {
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl<'a> PayloadReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// Чтение заданного количества байтов без преобразования
+    pub fn get_bytes(&mut self, count: usize) -> Result<&'a [u8], FrameError> {
+        if self.remaining() < count {
+            return Err(FrameError::PayloadTooShort {
+                needed: count,
+                remaining: self.remaining(),
+            });
+        }
+
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+        Ok(bytes)
+    }
+
+    /// Количество непрочитанных байтов
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    /// Оставшиеся непрочитанные байты
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_little_endian_fields() {
+        let bytes = PayloadWriter::new()
+            .put_u8(0x01)
+            .put_u16(0x1234)
+            .put_i16(-2)
+            .put_u32(0xDEADBEEF)
+            .put_bytes(b"ok")
+            .into_bytes();
+
+        assert_eq!(
+            bytes,
+            vec![
+                0x01, 0x34, 0x12, 0xFE, 0xFF, 0xEF, 0xBE, 0xAD, 0xDE, b'o', b'k'
+            ]
+        );
+
+        let mut reader = PayloadReader::new(&bytes);
+        assert_eq!(reader.get_u8(), Ok(0x01));
+        assert_eq!(reader.get_u16(), Ok(0x1234));
+        assert_eq!(reader.get_i16(), Ok(-2));
+        assert_eq!(reader.get_u32(), Ok(0xDEADBEEF));
+        assert_eq!(reader.rest(), b"ok");
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn test_short_payload() {
+        let mut reader = PayloadReader::new(&[0x01, 0x02, 0x03]);
+        assert_eq!(reader.get_u16(), Ok(0x0201));
+        assert_eq!(
+            reader.get_u32(),
+            Err(FrameError::PayloadTooShort {
+                needed: 4,
+                remaining: 1
+            })
+        );
+        assert_eq!(reader.get_u8(), Ok(0x03));
+    }
+}
@@ -0,0 +1,300 @@
+use log::{debug, warn};
+
+use crate::checksum::Checksum;
+use crate::connection::{ConnectionMonitor, ConnectionState};
+use crate::decoder::FrameDecoder;
+use crate::error::ClientError;
+use crate::fragment::{Message, Reassembler};
+use crate::opcode::Opcode;
+use crate::transport::Transport;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Период опроса канала фоновым потоком чтения
+const READER_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Обработчик сообщений определенного класса (опкода)
+pub type MessageHandler = Box<dyn FnMut(&Message) + Send + 'static>;
+
+/// Распределение принятых сообщений между ожидающим запросом и подписчиками
+pub(crate) struct Router {
+    /// Опкоды и номера ответов, которых ждут отправленные запросы
+    awaiting: Vec<(Opcode, Option<u8>)>,
+    replies: Sender<Result<Message, ClientError>>,
+    handlers: HashMap<Opcode, MessageHandler>,
+    subscribers: Vec<Sender<Message>>,
+    /// Алгоритм контрольной суммы, действующий после ответа на текущий запрос
+    checksum_on_reply: Option<Checksum>,
+    /// Поток чтения остановлен из-за ошибки канала
+    closed: bool,
+}
+
+impl Router {
+    fn new(replies: Sender<Result<Message, ClientError>>) -> Self {
+        Self {
+            awaiting: Vec::new(),
+            replies,
+            handlers: HashMap::new(),
+            subscribers: Vec::new(),
+            checksum_on_reply: None,
+            closed: false,
+        }
+    }
+
+    /// Ожидание ответа с заданным опкодом и номером запроса
+    pub(crate) fn expect_reply(
+        &mut self,
+        opcode: Opcode,
+        id: Option<u8>,
+    ) -> Result<(), ClientError> {
+        if self.closed {
+            return Err(ClientError::ConnectionClosed);
+        }
+        self.awaiting.push((opcode, id));
+        Ok(())
+    }
+
+    /// Прекращение ожидания ответов (запросы завершены)
+    ///
+    /// Опоздавшие ответы с номером запроса после этого отбрасываются
+    pub(crate) fn clear_expected(&mut self) {
+        self.awaiting.clear();
+    }
+
+    /// Смена контрольной суммы сразу после приема ответа (`None` - отмена)
+    ///
+    /// Пакеты, следующие за ответом, разбираются уже с новым алгоритмом
+    pub(crate) fn switch_checksum_on_reply(&mut self, checksum: Option<Checksum>) {
+        self.checksum_on_reply = checksum;
+    }
+
+    pub(crate) fn register_handler(
+        &mut self,
+        opcode: Opcode,
+        handler: MessageHandler,
+    ) -> Option<MessageHandler> {
+        self.handlers.insert(opcode, handler)
+    }
+
+    pub(crate) fn remove_handler(&mut self, opcode: Opcode) -> Option<MessageHandler> {
+        self.handlers.remove(&opcode)
+    }
+
+    /// Извлечение всех обработчиков (перенос в новое соединение)
+    pub(crate) fn take_handlers(&mut self) -> HashMap<Opcode, MessageHandler> {
+        std::mem::take(&mut self.handlers)
+    }
+
+    /// Возврат обработчиков, извлеченных `take_handlers`
+    pub(crate) fn restore_handlers(&mut self, handlers: HashMap<Opcode, MessageHandler>) {
+        self.handlers.extend(handlers);
+    }
+
+    /// Подписка на сообщения, не являющиеся ответами на запросы
+    pub(crate) fn subscribe(&mut self) -> Receiver<Message> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Передача сообщения получателю
+    ///
+    /// Возвращает алгоритм контрольной суммы, на который следует перейти
+    fn route(&mut self, message: Message) -> Option<Checksum> {
+        let key = (message.get_opcode(), message.get_id());
+        if let Some(index) = self.awaiting.iter().position(|awaited| *awaited == key) {
+            self.awaiting.remove(index);
+            let _ = self.replies.send(Ok(message));
+            return self.checksum_on_reply.take();
+        }
+
+        // Ответ на запрос, ожидание которого уже прекращено (таймаут, повтор)
+        if let Some(id) = message.get_id() {
+            warn!("Stale reply discarded: {} #{}", message.get_opcode(), id);
+            return None;
+        }
+
+        let handled = match self.handlers.get_mut(&message.get_opcode()) {
+            Some(handler) => {
+                debug!("Dispatching message: {}", message.get_opcode());
+                handler(&message);
+                true
+            }
+            None => false,
+        };
+
+        // Отписавшиеся (закрывшие приемник) подписчики удаляются
+        self.subscribers
+            .retain(|subscriber| subscriber.send(message.clone()).is_ok());
+
+        if !handled && self.subscribers.is_empty() {
+            warn!("Unhandled message: {}", message.get_opcode());
+        }
+
+        None
+    }
+
+    /// Передача ошибки канала ожидающему запросу
+    fn fail(&mut self, error: ClientError) {
+        self.closed = true;
+        // Подписчики узнают о закрытии канала по отключению приемника
+        self.subscribers.clear();
+        if !self.awaiting.is_empty() {
+            self.awaiting.clear();
+            let _ = self.replies.send(Err(error));
+        }
+    }
+}
+
+/// Состояние, общее для клиента и потока чтения
+pub(crate) struct Shared {
+    router: Mutex<Router>,
+    inter_byte: Mutex<Duration>,
+    checksum: Mutex<Checksum>,
+    /// Момент приема последнего пакета
+    last_received: Mutex<Instant>,
+    connection: Arc<ConnectionMonitor>,
+    running: AtomicBool,
+}
+
+impl Shared {
+    pub(crate) fn router(&self) -> MutexGuard<'_, Router> {
+        lock(&self.router)
+    }
+
+    pub(crate) fn set_inter_byte(&self, inter_byte: Duration) {
+        *lock(&self.inter_byte) = inter_byte;
+    }
+
+    pub(crate) fn get_checksum(&self) -> Checksum {
+        *lock(&self.checksum)
+    }
+
+    /// Смена алгоритма контрольной суммы принимаемых пакетов
+    pub(crate) fn set_checksum(&self, checksum: Checksum) {
+        *lock(&self.checksum) = checksum;
+    }
+
+    /// Время с момента приема последнего пакета
+    pub(crate) fn idle_time(&self) -> Duration {
+        lock(&self.last_received).elapsed()
+    }
+}
+
+/// Фоновый поток чтения пакетов и сборки сообщений
+///
+/// Ответы на запросы передаются в канал, возвращаемый `spawn`,
+/// остальные сообщения - обработчикам и подписчикам
+pub(crate) struct BackgroundReader {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BackgroundReader {
+    pub(crate) fn spawn(
+        transport: Box<dyn Transport>,
+        inter_byte: Duration,
+        checksum: Checksum,
+        connection: Arc<ConnectionMonitor>,
+    ) -> Result<(Self, Receiver<Result<Message, ClientError>>), ClientError> {
+        let (replies, receiver) = mpsc::channel();
+
+        let shared = Arc::new(Shared {
+            router: Mutex::new(Router::new(replies)),
+            inter_byte: Mutex::new(inter_byte),
+            checksum: Mutex::new(checksum),
+            last_received: Mutex::new(Instant::now()),
+            connection,
+            running: AtomicBool::new(true),
+        });
+
+        let thread_shared = shared.clone();
+        let thread = thread::Builder::new()
+            .name("mu-reader".to_string())
+            .spawn(move || read_loop(transport, &thread_shared))?;
+
+        Ok((
+            Self {
+                shared,
+                thread: Some(thread),
+            },
+            receiver,
+        ))
+    }
+
+    pub(crate) fn shared(&self) -> &Arc<Shared> {
+        &self.shared
+    }
+
+    /// Поток чтения работает (канал не закрыт)
+    pub(crate) fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Остановка потока чтения (канал чтения закрывается)
+    pub(crate) fn stop(&mut self) {
+        self.shared.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for BackgroundReader {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn read_loop(mut transport: Box<dyn Transport>, shared: &Shared) {
+    let mut decoder = FrameDecoder::new();
+    let mut reassembler = Reassembler::new();
+
+    while shared.running.load(Ordering::Relaxed) {
+        let inter_byte = *lock(&shared.inter_byte);
+        decoder.set_checksum(*lock(&shared.checksum));
+
+        match crate::recv_proto_message(
+            &mut transport,
+            &mut decoder,
+            Instant::now() + READER_POLL_INTERVAL,
+            inter_byte,
+        ) {
+            Ok(frame) => {
+                *lock(&shared.last_received) = Instant::now();
+
+                match reassembler.push(frame) {
+                    Ok(Some(message)) => {
+                        // Смена контрольной суммы до разбора следующих пакетов
+                        if let Some(checksum) = shared.router().route(message) {
+                            shared.set_checksum(checksum);
+                            decoder.set_checksum(checksum);
+                        }
+                    }
+                    Ok(None) => (),
+                    // Сообщение отброшено, ожидающий запрос повторится по таймауту
+                    Err(e) => warn!("Message dropped: {}", e),
+                }
+            }
+            Err(ClientError::Timeout) => (),
+            Err(e) => {
+                warn!("Reader stopped: {}", e);
+                shared.router().fail(e);
+                shared.connection.set(ConnectionState::Lost);
+                return;
+            }
+        }
+    }
+}
+
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,471 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::error::ClientError;
+
+/// Префикс адреса TCP соединения (например, преобразователь serial-to-Ethernet)
+pub const TCP_PREFIX: &str = "tcp://";
+/// Префикс адреса Unix сокета
+pub const UNIX_PREFIX: &str = "unix://";
+
+/// Канал передачи байтов между хостом и устройством
+///
+/// Чтение должно завершаться ошибкой `TimedOut` (или `WouldBlock`) по истечении
+/// таймаута и возвращать 0 при закрытии соединения
+pub trait Transport: Read + Write + Send {
+    /// Установка таймаута чтения
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()>;
+
+    /// Текущий таймаут чтения
+    fn timeout(&self) -> Duration;
+
+    /// Человекочитаемое имя канала (для логов)
+    fn name(&self) -> String;
+
+    /// Сброс принятых, но еще не прочитанных данных
+    fn clear_input(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Второй дескриптор того же канала
+    ///
+    /// Нужен для чтения в фоновом потоке одновременно с отправкой запросов
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} can not be cloned", self.name()),
+        ))
+    }
+}
+
+/// Открытие канала по адресу
+///
+/// `tcp://host:port` и `unix:///path` открывают сокеты,
+/// любой другой адрес считается именем последовательного порта (в т.ч. PTY)
+pub fn open(
+    address: &str,
+    baudrate: u32,
+    timeout: Duration,
+) -> Result<Box<dyn Transport>, ClientError> {
+    let open_error = |source: io::Error| ClientError::PortOpen {
+        port: address.to_string(),
+        source,
+    };
+
+    if let Some(host) = address.strip_prefix(TCP_PREFIX) {
+        let transport = TcpTransport::connect(host, timeout).map_err(open_error)?;
+        return Ok(Box::new(transport));
+    }
+
+    #[cfg(unix)]
+    if let Some(path) = address.strip_prefix(UNIX_PREFIX) {
+        let transport = UnixTransport::connect(path, timeout).map_err(open_error)?;
+        return Ok(Box::new(transport));
+    }
+
+    let transport =
+        SerialTransport::open(address, baudrate, timeout).map_err(|e| open_error(e.into()))?;
+    Ok(Box::new(transport))
+}
+
+/// Последовательный порт (UART, USB-UART, PTY)
+pub struct SerialTransport {
+    port: Box<dyn serialport::SerialPort + 'static>,
+}
+
+impl SerialTransport {
+    pub fn open(port_name: &str, baudrate: u32, timeout: Duration) -> serialport::Result<Self> {
+        let port = serialport::new(port_name, baudrate)
+            .timeout(timeout)
+            .open()?;
+        Ok(Self { port })
+    }
+
+    pub fn from_port(port: Box<dyn serialport::SerialPort + 'static>) -> Self {
+        Self { port }
+    }
+
+    /// Создание пары связанных псевдотерминалов (master, slave)
+    ///
+    /// Имя slave стороны можно передать стороннему процессу как имя порта
+    #[cfg(unix)]
+    pub fn pty_pair() -> serialport::Result<(Self, Self)> {
+        let (master, slave) = serialport::TTYPort::pair()?;
+        Ok((
+            Self::from_port(Box::new(master)),
+            Self::from_port(Box::new(slave)),
+        ))
+    }
+}
+
+impl Read for SerialTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.port.read(buf)
+    }
+}
+
+impl Write for SerialTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.port.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+impl Transport for SerialTransport {
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        Ok(self.port.set_timeout(timeout)?)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.port.timeout()
+    }
+
+    fn name(&self) -> String {
+        self.port.name().unwrap_or_else(|| "serial".to_string())
+    }
+
+    fn clear_input(&mut self) -> io::Result<()> {
+        Ok(self.port.clear(serialport::ClearBuffer::Input)?)
+    }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(Self::from_port(self.port.try_clone()?)))
+    }
+}
+
+/// TCP соединение
+pub struct TcpTransport {
+    stream: TcpStream,
+    timeout: Duration,
+}
+
+impl TcpTransport {
+    pub fn connect(address: &str, timeout: Duration) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(address)?, timeout)
+    }
+
+    pub fn from_stream(stream: TcpStream, timeout: Duration) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(timeout))?;
+        Ok(Self { stream, timeout })
+    }
+}
+
+impl Read for TcpTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Write for TcpTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Transport for TcpTransport {
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.stream.set_read_timeout(Some(timeout))?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn name(&self) -> String {
+        match self.stream.peer_addr() {
+            Ok(address) => format!("{}{}", TCP_PREFIX, address),
+            Err(_) => TCP_PREFIX.to_string(),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(Self {
+            stream: self.stream.try_clone()?,
+            timeout: self.timeout,
+        }))
+    }
+}
+
+/// Unix сокет
+#[cfg(unix)]
+pub struct UnixTransport {
+    stream: std::os::unix::net::UnixStream,
+    path: String,
+    timeout: Duration,
+}
+
+#[cfg(unix)]
+impl UnixTransport {
+    pub fn connect(path: &str, timeout: Duration) -> io::Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        Self::from_stream(stream, path, timeout)
+    }
+
+    pub fn from_stream(
+        stream: std::os::unix::net::UnixStream,
+        path: &str,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        stream.set_read_timeout(Some(timeout))?;
+        Ok(Self {
+            stream,
+            path: path.to_string(),
+            timeout,
+        })
+    }
+}
+
+#[cfg(unix)]
+impl Read for UnixTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+#[cfg(unix)]
+impl Write for UnixTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixTransport {
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.stream.set_read_timeout(Some(timeout))?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn name(&self) -> String {
+        format!("{}{}", UNIX_PREFIX, self.path)
+    }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(Self {
+            stream: self.stream.try_clone()?,
+            path: self.path.clone(),
+            timeout: self.timeout,
+        }))
+    }
+}
+
+/// Канал в памяти для тестов и симуляторов
+///
+/// Создается парой: данные, записанные в один конец, читаются из другого.
+/// Копии одного конца (`try_clone`) разделяют входящие данные
+pub struct MemoryPipe {
+    sender: Sender<Vec<u8>>,
+    incoming: Arc<Mutex<Incoming>>,
+    timeout: Duration,
+}
+
+/// Входящие данные конца канала
+struct Incoming {
+    receiver: Receiver<Vec<u8>>,
+    pending: VecDeque<u8>,
+}
+
+impl MemoryPipe {
+    pub fn pair(timeout: Duration) -> (Self, Self) {
+        let (host_sender, device_receiver) = mpsc::channel();
+        let (device_sender, host_receiver) = mpsc::channel();
+
+        (
+            Self::new(host_sender, host_receiver, timeout),
+            Self::new(device_sender, device_receiver, timeout),
+        )
+    }
+
+    fn new(sender: Sender<Vec<u8>>, receiver: Receiver<Vec<u8>>, timeout: Duration) -> Self {
+        Self {
+            sender,
+            incoming: Arc::new(Mutex::new(Incoming {
+                receiver,
+                pending: VecDeque::new(),
+            })),
+            timeout,
+        }
+    }
+
+    fn incoming(&self) -> std::sync::MutexGuard<'_, Incoming> {
+        self.incoming
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Read for MemoryPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let timeout = self.timeout;
+        let mut incoming = self.incoming();
+
+        if incoming.pending.is_empty() {
+            match incoming.receiver.recv_timeout(timeout) {
+                Ok(chunk) => incoming.pending.extend(chunk),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "Pipe read timeout"));
+                }
+                // Другой конец закрыт
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+
+        let size = buf.len().min(incoming.pending.len());
+        for (target, byte) in buf.iter_mut().zip(incoming.pending.drain(..size)) {
+            *target = byte;
+        }
+        Ok(size)
+    }
+}
+
+impl Write for MemoryPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Pipe closed"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for MemoryPipe {
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn name(&self) -> String {
+        "memory".to_string()
+    }
+
+    fn clear_input(&mut self) -> io::Result<()> {
+        let mut incoming = self.incoming();
+        incoming.pending.clear();
+        while incoming.receiver.try_recv().is_ok() {}
+        Ok(())
+    }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(Self {
+            sender: self.sender.clone(),
+            incoming: self.incoming.clone(),
+            timeout: self.timeout,
+        }))
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        (**self).set_timeout(timeout)
+    }
+
+    fn timeout(&self) -> Duration {
+        (**self).timeout()
+    }
+
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn clear_input(&mut self) -> io::Result<()> {
+        (**self).clear_input()
+    }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        (**self).try_clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_memory_pipe() {
+        let (mut host, mut device) = MemoryPipe::pair(Duration::from_millis(50));
+
+        host.write_all(b"hello").unwrap();
+        let mut buf = [0; 3];
+        assert_eq!(device.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf, b"hel");
+        assert_eq!(device.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"lo");
+
+        let error = device.read(&mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        drop(host);
+        assert_eq!(device.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_memory_pipe_clone() {
+        let (host, mut device) = MemoryPipe::pair(Duration::from_millis(50));
+        let mut reader = host.try_clone().unwrap();
+        let mut writer = host;
+
+        writer.write_all(b"ping").unwrap();
+        let mut buf = [0; 4];
+        device.read_exact(&mut buf).unwrap();
+
+        device.write_all(b"pong").unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+    }
+
+    #[test]
+    fn test_open_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("{}{}", TCP_PREFIX, listener.local_addr().unwrap());
+
+        let mut transport = open(&address, 9600, Duration::from_millis(50)).unwrap();
+        let (mut peer, _) = listener.accept().unwrap();
+
+        peer.write_all(b"Hi!").unwrap();
+        let mut buf = [0; 3];
+        transport.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hi!");
+        assert_eq!(transport.timeout(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_open_missing_port() {
+        let result = open("/dev/does-not-exist", 9600, Duration::from_millis(50));
+        assert!(matches!(result, Err(ClientError::PortOpen { .. })));
+    }
+}
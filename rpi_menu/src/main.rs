@@ -1,12 +1,16 @@
 use clap::Parser;
 
 use enigo::{Direction::Click, Enigo, Key, Keyboard, Settings};
-use misc::config::ConfigIO;
+use misc::config::{ConfigIO, set_config_root};
 use rppal::gpio::{Event, Gpio, Trigger};
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
+mod error;
 mod menu;
 
+use error::MenuError;
 use menu::{MainMenuStates, show_main_dialog};
 use misc::device_config::DeviceConfig;
 
@@ -16,6 +20,9 @@ struct Args {
     /// Имя конфиг файла
     #[arg(short = 'c', long = "config")]
     config_name: String,
+    /// Корневой каталог конфигов (подкаталоги device/ и serial/)
+    #[arg(long = "config-root")]
+    config_root: Option<PathBuf>,
 }
 
 /// BCM номер порта кнопки ввода
@@ -24,21 +31,36 @@ const IN_BTN: u8 = 7;
 const SEL_BTN: u8 = 8;
 
 /// Обработчик нажатия кнопки ввода
-fn in_clicked_handler(event: Event, activity_flag: Arc<Mutex<bool>>) {
+fn in_clicked_handler(_event: Event, activity_flag: Arc<Mutex<bool>>) {
     let mut enigo = Enigo::new(&Settings::default()).unwrap();
     enigo.key(Key::DownArrow, Click).unwrap();
     *activity_flag.lock().unwrap() = true;
 }
 
 /// Обработчик нажатия кнопки выбора
-fn sel_clicked_handler(event: Event, activity_flag: Arc<Mutex<bool>>) {
+fn sel_clicked_handler(_event: Event, activity_flag: Arc<Mutex<bool>>) {
     let mut enigo = Enigo::new(&Settings::default()).unwrap();
     enigo.key(Key::Return, Click).unwrap();
     *activity_flag.lock().unwrap() = true;
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
     let args = Args::parse();
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            e.exit_code()
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), MenuError> {
+    if let Some(root) = &args.config_root {
+        set_config_root(root);
+    }
+
     let mut device_config = DeviceConfig::create_from_existing(args.config_name.as_str())?;
 
     // Признак активности (пользователь всё ещё устанавливает параметры)
@@ -72,7 +94,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Ok(MainMenuStates::ExitState) => {
                 break;
             }
-            Err(e) => return Err(e.into()),
+            Err(e) => return Err(e),
         }
     }
     device_config.save_parameters()?;